@@ -1,16 +1,12 @@
+pub mod programs;
 pub mod shell;
-use std::sync::Arc;
-use parking_lot::Mutex;
+use starfleet::engine::{Engine, EngineHandle};
 
 fn main() {
-    let engine = Arc::new(Mutex::new(starfleet::Engine::new_empty()));
-    let engine_mutex = engine.clone();
-    let (sender, reciever) = std::sync::mpsc::channel();
-    let sender_clone = sender.clone();
-    let shell = shell::Shell::new(sender);
+    let (engine, reciever) = EngineHandle::new(Engine::new_empty());
+    let mut shell = shell::Shell::new(engine.clone());
+    shell.programs.insert("dump".to_owned(), programs::dump);
     //Spawn a thread for systems running
-    std::thread::spawn(move || {
-        starfleet::Engine::run(engine_mutex, sender_clone, reciever)
-    });
-    shell.run(engine.clone()).unwrap(); //Dedicate this thread to user interaction
+    engine.spawn(reciever);
+    shell.run().unwrap(); //Dedicate this thread to user interaction
 }
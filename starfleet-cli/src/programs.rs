@@ -0,0 +1,26 @@
+//! Programs that can be registered to run from the [Shell](crate::shell::Shell)
+use std::io::Write;
+
+use starfleet::engine::EngineHandle;
+use termcolor::{Color, ColorSpec, StandardStream, WriteColor};
+
+/// Print every entity in the world with its components, highlighting the line naming each entity
+pub fn dump(engine: &EngineHandle, _: &[String], stdout: &mut StandardStream) -> i32 {
+    let dump = engine.with(|engine| engine.dump());
+    match write_dump(&dump, stdout) {
+        Ok(()) => 0,
+        Err(_) => 1,
+    }
+}
+
+/// Write a dump made by [Engine::dump](starfleet::Engine::dump) to the shell's output
+fn write_dump(dump: &str, stdout: &mut StandardStream) -> Result<(), std::io::Error> {
+    for line in dump.lines() {
+        if !line.starts_with(' ') {
+            stdout.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true))?;
+        }
+        stdout.write_fmt(format_args!("{}\n", line))?;
+        stdout.reset()?;
+    }
+    Ok(())
+}
@@ -1,47 +1,47 @@
 //! The [Shell] struct emulates a real shell, parsing commands with the [shellwords] crate and passing them to 
 //! registered programs
-use std::{collections::HashMap, io::Write, sync::mpsc::Sender};
+use std::{collections::HashMap, io::Write};
 
-use starfleet::{engine::Engine, event::Event};
-use std::sync::Arc;
-use parking_lot::Mutex;
+use starfleet::{engine::EngineHandle, event::Event};
 use termcolor::{StandardStream, Color, WriteColor, ColorChoice, ColorSpec};
 
+/// A program that can be run from the shell, taking in a handle to the engine for reading game state and
+/// sending events and queries, and command line arguments and returning an exit code
+pub type Program = fn(&EngineHandle, &[String], &mut StandardStream) -> i32;
+
 /// A struct that parses commands given to the program and runs the appropriate 
 /// programs
 #[derive(Clone)]
 pub struct Shell {
     /// A map of program names to functions to run that take in the game state and command
     /// line arguments to produce a result
-    pub programs: HashMap<String, fn(Arc<Mutex<Engine>>, &[String], &mut StandardStream) -> i32>,
+    pub programs: HashMap<String, Program>,
 
-    /// Event sender for sending the EXIT event
-    sender: Sender<Event>,
+    /// Handle to the engine for sending the EXIT event and running programs
+    engine: EngineHandle,
 }
 
 impl Shell {
-    /// Create a new [Shell] with the given event channel
-    pub fn new(sender: Sender<Event>) -> Self {
+    /// Create a new [Shell] running programs on the given engine
+    pub fn new(engine: EngineHandle) -> Self {
         Self {
-            sender,
+            engine,
             programs: HashMap::new()
         }
     }
 
     /// Loop endlessly, sending the EXIT event when the exit command is encountered
-    pub fn run(&self, engine: Arc<Mutex<Engine>>) -> Result<(), std::io::Error> {
+    pub fn run(&self) -> Result<(), std::io::Error> {
         let mut stdout = StandardStream::stdout(ColorChoice::Auto);     
         loop {
             let mut line = String::new();
             stdout.write_all(b"> ")?;
             stdout.flush()?;
-            let stdin = std::io::stdin();
-            stdin.read_line(&mut line)?;
-            drop(stdin);
+            std::io::stdin().read_line(&mut line)?;
             let words = match shellwords::split(&line) {
-                Ok(words) if words.len() > 0 => words,
+                Ok(words) if !words.is_empty() => words,
                 Ok(_) => {
-                    stdout.write_all(&[b'\n'])?;
+                    stdout.write_all(b"\n")?;
                     continue
                 }   
                 Err(_) => {
@@ -54,11 +54,11 @@ impl Shell {
 
             match words[0].as_str() {
                 "exit" => {
-                    self.sender.send(Event::Exit).unwrap();
+                    self.engine.send_event(Event::Exit).unwrap();
                     break
                 },
                 other => match self.programs.get(other) {
-                    Some(prog) => { (prog)(engine.clone(), &words, &mut stdout); },
+                    Some(prog) => { (prog)(&self.engine, &words, &mut stdout); },
                     None => {
                         stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red)).set_bold(true))?;
                         stdout.write_fmt(format_args!("Error when running program: Command or program '{}' does not exist\n", &words[0]))?;
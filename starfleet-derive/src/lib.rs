@@ -5,6 +5,7 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use syn::parse::Parse;
 use syn::spanned::Spanned;
+use syn::punctuated::Punctuated;
 use syn::{
     parse::Parser, parse_macro_input, Item, ItemEnum, ItemFn, ItemStruct, ItemType, ItemUnion,
     Token,
@@ -25,24 +26,89 @@ fn fnv1a(bytes: &[u8]) -> u64 {
     hash
 }
 
+/// Get the hash used as the ID of a component, prefixing the name with its namespace if it has one so
+/// that components from different namespaces never share an ID
+fn component_hash(namespace: Option<&str>, name: &str) -> u64 {
+    match namespace {
+        Some(namespace) => fnv1a(format!("{}::{}", namespace, name).as_bytes()),
+        None => fnv1a(name.as_bytes()),
+    }
+}
+
+/// The name of every schedule that systems can be registered to run on, which must be kept in sync with
+/// the `Event` enum and the fields of `SchedulesBuilder` in the `starfleet` crate
+const EVENTS: &[&str] = &["tick"];
+
+/// Get an error message if systems can't be registered to run on the given event
+fn invalid_event(event: &str) -> Option<String> {
+    match EVENTS.contains(&event.to_lowercase().as_str()) {
+        true => None,
+        false => Some(format!(
+            "Unknown event `{}`, expected one of: {}",
+            event,
+            EVENTS.join(", ")
+        )),
+    }
+}
+
 lazy_static! {
     /// A set of all used hash values, used to detect collisions at compile time
     static ref HASHES: Arc<Mutex<HashMap<u64, String>>> = Arc::new(Mutex::new(HashMap::new()));
 }
 
 /// Attributes given as arguments to a procedural macro
-struct Attrs(pub HashMap<String, String>);
+struct Attrs {
+    /// Arguments given as `name = "value"`
+    values: HashMap<String, String>,
+    /// Arguments given as `name(field = "value", other)`, as a list of field names and their optional values
+    lists: HashMap<String, Vec<FieldArg>>,
+}
+
+/// A field name with an optional value given in a list of procedural macro arguments
+struct FieldArg {
+    /// The name of the field
+    field: syn::Ident,
+    /// The string given after `=`, if any
+    value: Option<String>,
+}
+
+impl Parse for FieldArg {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let field = input.parse()?;
+        let value = match input.peek(Token![=]) {
+            true => {
+                let _: Token![=] = input.parse()?;
+                let string: syn::LitStr = input.parse()?;
+                Some(string.value())
+            }
+            false => None,
+        };
+        Ok(Self { field, value })
+    }
+}
 
 impl Parse for Attrs {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        let mut map = HashMap::new();
+        let mut values = HashMap::new();
+        let mut lists = HashMap::new();
         loop {
             if input.is_empty() {
                 break;
             }
             let ident: syn::Ident = input.parse()?;
-            let _: Token![=] = input.parse()?;
-            let string: syn::LitStr = input.parse()?;
+            match input.peek(syn::token::Paren) {
+                true => {
+                    let content;
+                    syn::parenthesized!(content in input);
+                    let args = Punctuated::<FieldArg, Token![,]>::parse_terminated(&content)?;
+                    lists.insert(ident.to_string(), args.into_iter().collect());
+                }
+                false => {
+                    let _: Token![=] = input.parse()?;
+                    let string: syn::LitStr = input.parse()?;
+                    values.insert(ident.to_string(), string.value());
+                }
+            }
             match input.peek(Token![,]) {
                 true => {
                     let _: Token![,] = input.parse()?;
@@ -56,13 +122,59 @@ impl Parse for Attrs {
                     }
                 }
             }
-            map.insert(ident.to_string(), string.value());
         }
-        Ok(Self(map))
+        Ok(Self { values, lists })
+    }
+}
+
+/// Get the field with the given name, or an error spanned to the name if there is none
+fn find_field<'a>(
+    fields: &'a mut Punctuated<syn::Field, Token![,]>,
+    name: &syn::Ident,
+) -> syn::Result<&'a mut syn::Field> {
+    fields
+        .iter_mut()
+        .find(|field| field.ident.as_ref() == Some(name))
+        .ok_or_else(|| syn::Error::new(name.span(), format!("No field named `{}` in component", name)))
+}
+
+/// Add `#[serde(rename = "...")]` to every field named in the `field_renames` argument and `#[serde(default)]`
+/// to every field named in the `field_defaults` argument of the component macro
+fn apply_field_attrs(item: &mut Item, attrs: &Attrs) -> syn::Result<()> {
+    let renames = attrs.lists.get("field_renames").map(Vec::as_slice).unwrap_or_default();
+    let defaults = attrs.lists.get("field_defaults").map(Vec::as_slice).unwrap_or_default();
+    if renames.is_empty() && defaults.is_empty() {
+        return Ok(());
+    }
+    let fields = match item {
+        Item::Struct(ItemStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => &mut fields.named,
+        other => {
+            return Err(syn::Error::new(
+                other.span(),
+                "Field renames and defaults can only be given for structs with named fields",
+            ))
+        }
+    };
+    for FieldArg { field, value } in renames {
+        let rename = value.as_ref().ok_or_else(|| {
+            syn::Error::new(field.span(), "Expected the serialized name of the field, like `field = \"name\"`")
+        })?;
+        find_field(fields, field)?.attrs.push(syn::parse_quote!(#[serde(rename = #rename)]));
     }
+    for FieldArg { field, value } in defaults {
+        if value.is_some() {
+            return Err(syn::Error::new(field.span(), "Expected only the name of a field to default"));
+        }
+        find_field(fields, field)?.attrs.push(syn::parse_quote!(#[serde(default)]));
+    }
+    Ok(())
 }
 
-/// Register this as a component type for serialization and deserialization
+/// Register this as a component type for serialization and deserialization. The type must implement
+/// `Debug` so that it can be shown in dumps of the world
 /// ## Example
 /// ```ignore
 /// #[component]
@@ -81,16 +193,37 @@ impl Parse for Attrs {
 ///     health: f32
 /// }
 /// ```
+///
+/// Mods that need their own set of IDs can give a namespace with the `namespace = val` syntax, so that a
+/// component with the same name in another namespace, or with no namespace, is given a different ID
+///
+/// ```ignore
+/// #[component(namespace = "shipyard")]
+/// pub struct Health {
+///     health: f32
+/// }
+/// ```
+///
+/// Fields of a struct can keep the name they were saved with after being renamed by listing them in
+/// `field_renames`, and fields added after a component was saved can be listed in `field_defaults` to be
+/// loaded with their `Default` value when they are missing from the save
+///
+/// ```ignore
+/// #[component(field_renames(hp = "health"), field_defaults(regen))]
+/// pub struct Health {
+///     hp: f32,
+///     regen: f32,
+/// }
+/// ```
 #[proc_macro_attribute]
-pub fn component(attr: TokenStream, mut item: TokenStream) -> TokenStream {
+pub fn component(attr: TokenStream, item: TokenStream) -> TokenStream {
     let attrs = parse_macro_input!(attr as Attrs);
-    let def: TokenStream = item.clone().into();
-    let parsed = parse_macro_input!(def as Item);
-    let name = match parsed {
+    let mut parsed = parse_macro_input!(item as Item);
+    let name = match &parsed {
         Item::Enum(ItemEnum { ident, .. })
         | Item::Struct(ItemStruct { ident, .. })
         | Item::Type(ItemType { ident, .. })
-        | Item::Union(ItemUnion { ident, .. }) => ident,
+        | Item::Union(ItemUnion { ident, .. }) => ident.clone(),
         other => {
             return quote_spanned! {
                 other.span() =>
@@ -102,12 +235,21 @@ pub fn component(attr: TokenStream, mut item: TokenStream) -> TokenStream {
 
     name.span().start();
 
+    if let Err(e) = apply_field_attrs(&mut parsed, &attrs) {
+        return e.to_compile_error().into();
+    }
+
     //Get the name to hash for identifier, to fix hash collisions
-    let hash_name = match attrs.0.get("name") {
+    let hash_name = match attrs.values.get("name") {
         Some(name) => name.clone(),
         None => name.to_string(),
     };
-    let hash = fnv1a(hash_name.as_bytes()); //Get the hash of the chosen identifier for component ID
+    let namespace = attrs.values.get("namespace").map(String::as_str);
+    let hash = component_hash(namespace, &hash_name); //Get the hash of the chosen identifier for component ID
+    let hash_name = match namespace {
+        Some(namespace) => format!("{}::{}", namespace, hash_name),
+        None => hash_name,
+    };
 
     let mut hashes = HASHES.lock().unwrap(); //Get the collection of hashes to check for a collision
     match hashes.get(&hash) {
@@ -117,7 +259,7 @@ pub fn component(attr: TokenStream, mut item: TokenStream) -> TokenStream {
                 hash_name, other
             );
             return quote_spanned! {
-                name.span().into() =>
+                name.span() =>
                 compile_error!( #errmsg );
             }
             .into();
@@ -129,6 +271,10 @@ pub fn component(attr: TokenStream, mut item: TokenStream) -> TokenStream {
 
     let static_name = quote::format_ident!("_{}", hash);
     let register_fn_name = quote::format_ident!("_{}_register", hash);
+    let name_static_name = quote::format_ident!("_{}_NAME", hash);
+    let format_fn_name = quote::format_ident!("_{}_format", hash);
+    let format_static_name = quote::format_ident!("_{}_FORMATTER", hash);
+    let name_str = name.to_string();
 
     let component_impl = quote! {
         fn #register_fn_name (registry: &mut ::legion::serialize::Registry<u64>) {
@@ -143,8 +289,31 @@ pub fn component(attr: TokenStream, mut item: TokenStream) -> TokenStream {
         #[cfg(use_linkme)]
         #[::linkme::distributed_slice(crate::register::COMPONENT_HASHES)]
         static #static_name: fn(&mut ::legion::serialize::Registry<u64>) = #register_fn_name;
+
+        #[cfg(use_inventory)]
+        ::inventory::submit! {
+            crate::register::ComponentName( #hash, #name_str )
+        }
+
+        #[cfg(use_linkme)]
+        #[::linkme::distributed_slice(crate::register::COMPONENT_NAMES)]
+        static #name_static_name: (u64, &'static str) = (#hash, #name_str);
+
+        fn #format_fn_name (entry: &::legion::world::EntryRef) -> ::std::option::Option<::std::string::String> {
+            entry.get_component::<#name>().ok().map(|component| format!("{:?}", component))
+        }
+
+        #[cfg(use_inventory)]
+        ::inventory::submit! {
+            crate::register::ComponentFormatterFunction( #hash, #format_fn_name )
+        }
+
+        #[cfg(use_linkme)]
+        #[::linkme::distributed_slice(crate::register::COMPONENT_FORMATTERS)]
+        static #format_static_name: (u64, crate::register::ComponentFormatter) = (#hash, #format_fn_name);
     };
 
+    let mut item: TokenStream = quote!(#parsed).into();
     item.extend(TokenStream::from(component_impl));
     item
 }
@@ -153,7 +322,7 @@ pub fn component(attr: TokenStream, mut item: TokenStream) -> TokenStream {
 /// Requires an argument for the event name
 #[proc_macro_attribute]
 pub fn on_event(attr: TokenStream, mut item: TokenStream) -> TokenStream {
-    let def: TokenStream = item.clone().into();
+    let def: TokenStream = item.clone();
     let def: ItemFn = parse_macro_input!(def as ItemFn);
     let name = def.sig.ident;
 
@@ -161,7 +330,15 @@ pub fn on_event(attr: TokenStream, mut item: TokenStream) -> TokenStream {
     let parser = syn::punctuated::Punctuated::<syn::Ident, Token![,]>::parse_separated_nonempty;
     let list = parser.parse(attr).unwrap();
 
-    for event in list.iter().map(|ident| ident.to_string()) {
+    for ident in list.iter() {
+        let event = ident.to_string();
+        if let Some(errmsg) = invalid_event(&event) {
+            return quote_spanned! {
+                ident.span() =>
+                compile_error!( #errmsg );
+            }
+            .into();
+        }
         let register_fn_name = quote::format_ident!("_{}_register", name);
         let system_fn_name = quote::format_ident!("{}_system", name);
         let event_name = quote::format_ident!("{}", event.to_lowercase());
@@ -187,3 +364,70 @@ pub fn on_event(attr: TokenStream, mut item: TokenStream) -> TokenStream {
 
     item
 }
+
+/// Let a system return `Result<(), SystemError>`, collecting any returned error into the `Errors`
+/// resource instead of forcing the system to panic or ignore the error. Must be placed above the
+/// `#[legion::system]` attribute
+/// ## Example
+/// ```ignore
+/// #[fallible]
+/// #[legion::system]
+/// fn check_fuel(#[resource] fuel: &Fuel) -> Result<(), SystemError> {
+///     if fuel.amount < 0. {
+///         return Err(SystemError::new("Fuel is negative"));
+///     }
+///     Ok(())
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn fallible(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut def = parse_macro_input!(item as ItemFn);
+    let name = def.sig.ident.to_string();
+    let output = match std::mem::replace(&mut def.sig.output, syn::ReturnType::Default) {
+        syn::ReturnType::Type(_, ty) => ty,
+        syn::ReturnType::Default => {
+            return quote_spanned! {
+                def.sig.span() =>
+                compile_error!("Expected a fallible system to return Result<(), SystemError>");
+            }
+            .into()
+        }
+    };
+    def.sig.inputs.push(syn::parse_quote! {
+        #[resource] __errors: &crate::system::Errors
+    });
+
+    //Run the body in a closure so that `?` and `return` still produce the result
+    let body = &def.block;
+    def.block = syn::parse_quote! {{
+        #[allow(clippy::redundant_closure_call)]
+        let __result: #output = (|| #body)();
+        if let ::std::result::Result::Err(e) = __result {
+            __errors.push(#name, e);
+        }
+    }};
+    quote!(#def).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_invalid_event() {
+        assert_eq!(invalid_event("tick"), None);
+        assert_eq!(invalid_event("Tick"), None);
+        assert_eq!(
+            invalid_event("tikc"),
+            Some("Unknown event `tikc`, expected one of: tick".to_owned())
+        );
+    }
+
+    #[test]
+    pub fn test_component_hash() {
+        assert_eq!(component_hash(None, "Health"), fnv1a(b"Health"));
+        assert_eq!(component_hash(Some("shipyard"), "Health"), fnv1a(b"shipyard::Health"));
+        assert_ne!(component_hash(Some("shipyard"), "Health"), component_hash(Some("drydock"), "Health"));
+        assert_ne!(component_hash(Some("shipyard"), "Health"), component_hash(None, "Health"));
+    }
+}
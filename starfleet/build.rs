@@ -1,4 +1,6 @@
 fn main() {
+    println!("cargo:rustc-check-cfg=cfg(use_linkme)");
+    println!("cargo:rustc-check-cfg=cfg(use_inventory)");
     if cfg!(any(
         target_os = "macos",
         target_os = "linux",
@@ -1,17 +1,136 @@
 //! Components and related structs for the hulls of ships, stations, etc.
+use legion::Entity;
 use serde::{Serialize, Deserialize};
+use std::fmt;
 
 /// The `Hull` struct is the base component for all entities that have some kind
 /// of hull, wether a ship or station.
-/// 
+///
 /// It determines things like what components can be fitted to the entity
 #[crate::component]
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Hull {
-    
+    /// The size of the hull, limiting how many modules can be fitted
+    pub size: HullSize,
+    /// All module entities currently fitted to this hull
+    fitted: Vec<Entity>,
+}
+
+impl Hull {
+    /// Create a new `Hull` of the given size with no modules fitted
+    pub fn new(size: HullSize) -> Self {
+        Self {
+            size,
+            fitted: Vec::with_capacity(size.slots()),
+        }
+    }
+
+    /// Fit a module entity to this hull, returning an error if the hull has no free slots
+    /// or the module is already fitted
+    pub fn fit(&mut self, module: Entity) -> Result<(), FitError> {
+        if self.fitted.contains(&module) {
+            return Err(FitError::AlreadyFitted(module));
+        }
+        if self.fitted.len() >= self.size.slots() {
+            return Err(FitError::NoFreeSlots(self.size));
+        }
+        self.fitted.push(module);
+        Ok(())
+    }
+
+    /// Remove a fitted module from this hull, returning an error if the module was never fitted
+    pub fn unfit(&mut self, module: Entity) -> Result<(), FitError> {
+        match self.fitted.iter().position(|fitted| *fitted == module) {
+            Some(idx) => {
+                self.fitted.remove(idx);
+                Ok(())
+            }
+            None => Err(FitError::NotFitted(module)),
+        }
+    }
+
+    /// Get all modules fitted to this hull
+    #[inline(always)]
+    pub fn fitted(&self) -> &[Entity] {
+        &self.fitted
+    }
+
+    /// Get the number of slots that are still free for fitting modules
+    #[inline(always)]
+    pub fn free_slots(&self) -> usize {
+        self.size.slots() - self.fitted.len()
+    }
 }
 
 /// The size of a hull cateforized into an enum
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum HullSize {
     Tiny,
-}
\ No newline at end of file
+    Small,
+    Medium,
+    Large,
+}
+
+impl HullSize {
+    /// Get the number of module slots that a hull of this size has
+    pub const fn slots(&self) -> usize {
+        match self {
+            Self::Tiny => 1,
+            Self::Small => 3,
+            Self::Medium => 6,
+            Self::Large => 12,
+        }
+    }
+}
+
+/// An error returned when fitting or unfitting a module to a [Hull] fails
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FitError {
+    /// All slots of a hull with the given size are already filled
+    NoFreeSlots(HullSize),
+    /// The module is already fitted to the hull
+    AlreadyFitted(Entity),
+    /// The module is not fitted to the hull
+    NotFitted(Entity),
+}
+
+impl fmt::Display for FitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoFreeSlots(size) => write!(f, "{:?} hull has no free slots out of {}", size, size.slots()),
+            Self::AlreadyFitted(module) => write!(f, "Module {:?} is already fitted to the hull", module),
+            Self::NotFitted(module) => write!(f, "Module {:?} is not fitted to the hull", module),
+        }
+    }
+}
+
+impl std::error::Error for FitError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use legion::World;
+
+    #[test]
+    pub fn test_fit() {
+        let mut world = World::default();
+        let mut hull = Hull::new(HullSize::Small);
+        let modules = (0..HullSize::Small.slots())
+            .map(|_| world.push(()))
+            .collect::<Vec<_>>();
+        for module in modules.iter() {
+            assert_eq!(hull.fit(*module), Ok(()));
+        }
+        assert_eq!(hull.fitted(), modules.as_slice());
+        assert_eq!(hull.free_slots(), 0);
+
+        let extra = world.push(());
+        assert_eq!(hull.fit(extra), Err(FitError::NoFreeSlots(HullSize::Small)));
+        assert_eq!(hull.fitted().len(), HullSize::Small.slots());
+
+        hull.unfit(modules[0]).unwrap();
+        assert_eq!(hull.unfit(modules[0]), Err(FitError::NotFitted(modules[0])));
+        assert_eq!(hull.fit(extra), Ok(()));
+        assert_eq!(hull.fit(extra), Err(FitError::AlreadyFitted(extra)));
+    }
+}
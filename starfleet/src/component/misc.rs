@@ -21,3 +21,36 @@ pub struct Location {
     pub loc: Point,
 }
 
+
+/// The star system an entity is in, changing the name moves the entity to the named star system
+/// the next time its [Location] is synchronized
+#[component]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct InSystem {
+    /// The name of the star system in the galaxy
+    pub name: String,
+}
+
+/// The [Location] and [InSystem] of an entity as of the last time star systems' spatial indices were
+/// synchronized, used to detect when either component changes
+#[component]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LastLocation {
+    /// The location of the entity in its star system's spatial index
+    pub loc: Point,
+    /// The name of the star system whose spatial index contains the entity
+    pub system: String,
+}
+
+/// The kind of natural body an entity is, given to the bodies placed by a
+/// [SystemTemplate](crate::state::SystemTemplate)
+#[component]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Body {
+    /// A star that other bodies orbit
+    Star,
+    /// A planet orbiting the stars of a star system
+    Planet,
+    /// A small rocky body, usually one of many in a belt
+    Asteroid,
+}
@@ -1 +1,11 @@
 //! Components for physics interactions
+use serde::{Deserialize, Serialize};
+
+/// The size of an entity for collision detection, treating the entity as a circle
+/// centered on its position in the star system
+#[crate::component]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Radius {
+    /// The radius of the entity
+    pub r: f32,
+}
@@ -11,3 +11,19 @@ pub struct Powered {
     pub pwr: Power,
 }
 
+/// A module fitted to a hull that draws power from the [Powered] entity it is fitted to
+/// while it is [Online]
+#[crate::component]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct PowerDraw {
+    /// The power this module draws while online
+    pub watts: Power,
+    /// The priority of this module when the power budget is exceeded, lower priority
+    /// modules are taken offline first
+    pub priority: u8,
+}
+
+/// Marker component for modules that are currently powered and operating
+#[crate::component]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Online;
@@ -3,12 +3,40 @@
 //! file
 
 //use crossbeam_channel::{Receiver, Sender};
-use std::sync::{mpsc::{Receiver, Sender}, atomic::{AtomicBool, self}, Arc};
-use legion::{serialize::Canon, Resources, Schedule, World};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::{self, Write as _},
+    fs,
+    io::{self, Read, Write},
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    sync::{
+        atomic::{self, AtomicBool},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+use indexmap::IndexMap;
+use legion::{
+    serialize::{set_entity_serializer, Canon},
+    Entity, IntoQuery, Resources, Schedule, World,
+};
 use parking_lot::Mutex;
-use serde::{ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
+use serde::{
+    de::DeserializeSeed,
+    ser::SerializeStruct,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 
-use crate::{event::Event, register, state::State};
+use crate::{
+    event::{Event, EventBus, EventLog, Query, Response},
+    register,
+    resources::{AutosaveConfig, CollisionConfig, GameRng, SimClock},
+    state::{Galaxy, Rect, StarSystem, State},
+    system::Errors,
+};
 
 /// The `Engine` struct handles any events raised by systems, contains all global state, and
 /// is responsible for serializing and deserializing the game state
@@ -18,8 +46,21 @@ pub struct Engine {
     world: World,
     /// All global game state
     state: State,
+    /// The random number generator shared by all systems
+    rng: GameRng,
+    /// The simulated time that the engine has been running
+    clock: SimClock,
+    /// The canon names of all entities, used so that entities are given the same name every time
+    /// the engine is serialized
+    canon: Canon,
+    /// The log that every processed event is recorded to, if recording was started with
+    /// [start_recording](Engine::start_recording). The log is never saved with the engine
+    log: Option<EventLog>,
 }
 
+/// The simulated time that passes every tick
+pub const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
 /// The `Schedules` struct holds a [Schedule](legion::Schedule) for each event that occurs
 #[derive(Debug)]
 pub struct Schedules {
@@ -28,41 +69,548 @@ pub struct Schedules {
 }
 
 impl Engine {
-    /// Create a totally empty world, used for debugging
+    /// Create a totally empty world with a random seed, used for debugging
     pub fn new_empty() -> Self {
+        Self::new_seeded(rand::random())
+    }
+
+    /// Create a totally empty world, seeding the [GameRng] resource with the given seed
+    pub fn new_seeded(seed: u64) -> Self {
         Self {
             world: World::default(),
-            state: State::default()
+            state: State::default(),
+            rng: GameRng::new(seed),
+            clock: SimClock::default(),
+            canon: Canon::default(),
+            log: None,
+        }
+    }
+
+    /// Get the [World] containing all entities and component data
+    #[inline(always)]
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Get the [World] containing all entities and component data mutably
+    #[inline(always)]
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    /// Get all global game state
+    #[inline(always)]
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// Get all global game state mutably
+    #[inline(always)]
+    pub fn state_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+
+    /// Get the number of ticks and simulated time that the engine has been running
+    #[inline(always)]
+    pub fn sim_clock(&self) -> SimClock {
+        self.clock
+    }
+
+    /// Execute a schedule on the world, inserting all resources that persist with the engine into
+    /// `resources` for the duration of the schedule
+    ///
+    /// If a system panics, the persistent resources are moved back into the engine before the panic
+    /// continues, but the [World] may be left with only some systems' changes applied
+    pub fn execute(&mut self, schedule: &mut Schedule, resources: &mut Resources) {
+        resources.insert(std::mem::take(&mut self.state));
+        resources.insert(std::mem::take(&mut self.rng));
+        resources.insert(self.clock);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| schedule.execute(&mut self.world, resources)));
+        self.state = resources.remove::<State>().unwrap_or_default();
+        self.rng = resources.remove::<GameRng>().unwrap_or_default();
+        resources.remove::<SimClock>();
+        if let Err(payload) = result {
+            panic::resume_unwind(payload);
+        }
+    }
+
+    /// Run all systems that run on a tick, then save the engine if an [AutosaveConfig] resource
+    /// is present and its interval has passed
+    pub fn tick(&mut self, schedules: &mut Schedules, resources: &mut Resources) -> Result<(), SaveError> {
+        self.execute(&mut schedules.tick, resources);
+        self.clock.advance(TICK_INTERVAL);
+        if let Some(mut autosave) = resources.get_mut::<AutosaveConfig>() {
+            autosave.since_save += TICK_INTERVAL;
+            if autosave.since_save >= autosave.interval {
+                autosave.since_save = Duration::ZERO;
+                self.save(&autosave.path)?;
+            }
         }
+        Ok(())
+    }
+
+    /// Handle an event sent to the event loop, recording it if [start_recording](Engine::start_recording) was
+    /// called. [Exit](Event::Exit) events must be handled by the caller
+    ///
+    /// [Query](Event::Query) and [Exit](Event::Exit) events never change the engine, so they are not recorded.
+    /// Returns an error only if a tick triggered an autosave that failed
+    pub fn process(&mut self, event: &Event, schedules: &mut Schedules, resources: &mut Resources) -> Result<(), SaveError> {
+        if let Some(log) = self.log.as_mut() {
+            if !matches!(event, Event::Query { .. } | Event::Exit) {
+                log.record(self.clock.ticks, event.clone());
+            }
+        }
+        match event {
+            Event::Tick => self.tick(schedules, resources),
+            Event::Query { req, reply } => {
+                //The thread that sent the query may have stopped waiting for a response
+                let _ = reply.send(self.query(*req));
+                Ok(())
+            }
+            //No systems respond to collisions or entities leaving star systems yet
+            Event::Exit | Event::Collision { .. } | Event::OutOfBounds { .. } => Ok(()),
+        }
+    }
+
+    /// Start recording every event processed by the engine to a new [EventLog], replacing the log if the
+    /// engine was already recording. Save the engine at the same time so the log can be
+    /// [replayed](Engine::replay) from the save
+    pub fn start_recording(&mut self) {
+        self.log = Some(EventLog::default());
+    }
+
+    /// Stop recording events, returning the log of every event processed since recording started or `None`
+    /// if the engine was not recording
+    pub fn stop_recording(&mut self) -> Option<EventLog> {
+        self.log.take()
+    }
+
+    /// Load the engine saved at `base_save` when recording started and process every event in `log` with
+    /// the given schedules, reproducing the recorded session
+    ///
+    /// The schedules and resources must match the ones used while recording for the replay to be exact.
+    /// Returns [Diverged](ReplayError::Diverged) if an event would be processed on a different tick than it
+    /// was recorded on, which happens when the save wasn't taken when recording started
+    pub fn replay(
+        log: &EventLog,
+        base_save: impl AsRef<Path>,
+        schedules: &mut Schedules,
+        resources: &mut Resources,
+    ) -> Result<Self, ReplayError> {
+        let mut engine = Self::load(base_save)?;
+        for (tick, event) in log.events() {
+            if engine.clock.ticks != *tick {
+                return Err(ReplayError::Diverged {
+                    recorded: *tick,
+                    replayed: engine.clock.ticks,
+                });
+            }
+            engine.process(event, schedules, resources)?;
+        }
+        Ok(engine)
+    }
+
+    /// Answer a [Query] for data from the engine
+    pub fn query(&self, req: Query) -> Response {
+        match req {
+            Query::EntityCount => Response::EntityCount(self.world.len()),
+            Query::SystemCount => Response::SystemCount(self.state.galaxy().len()),
+        }
+    }
+
+    /// Format every entity in the world with the name and value of each of its registered components,
+    /// for debugging a running engine without writing a save file
+    pub fn dump(&self) -> String {
+        let names = register::registered_components().into_iter().collect::<BTreeMap<_, _>>();
+        let formatters = register::component_formatters();
+
+        let mut dump = String::new();
+        for entity in Entity::query().iter(&self.world) {
+            let entry = match self.world.entry_ref(*entity) {
+                Ok(entry) => entry,
+                Err(_) => continue,
+            };
+            let _ = writeln!(dump, "{:?}", entity);
+            for (hash, format) in formatters.iter() {
+                if let Some(value) = format(&entry) {
+                    let _ = writeln!(dump, "    {}: {}", names.get(hash).copied().unwrap_or("<unnamed>"), value);
+                }
+            }
+        }
+        dump
+    }
+
+    /// Get the number of entities, component types, and star systems in the engine, and how large a save
+    /// file would be if the engine was saved now
+    pub fn stats(&self) -> Result<EngineStats, rmp_serde::encode::Error> {
+        let mut component_types = HashSet::new();
+        for entity in Entity::query().iter(&self.world) {
+            if let Ok(entry) = self.world.entry_ref(*entity) {
+                component_types.extend(entry.archetype().layout().component_types().iter().copied());
+            }
+        }
+        let mut counter = ByteCounter(0);
+        rmp_serde::encode::write(&mut counter, self)?;
+
+        Ok(EngineStats {
+            entities: self.world.len(),
+            component_types: component_types.len(),
+            systems: self.state.galaxy().len(),
+            save_size: counter.0,
+        })
+    }
+
+    /// Save the engine to a file, first writing to a temporary file next to the save file and then
+    /// replacing the save file so that a failed save never leaves a partially written save file. The
+    /// temporary file is removed if writing it fails
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        let path = path.as_ref();
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+
+        if let Err(e) = self.write_save(Path::new(&tmp_path)) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Write the engine to a new file, only returning once all data has been flushed to the file
+    fn write_save(&self, path: &Path) -> Result<(), SaveError> {
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        rmp_serde::encode::write(&mut file, self)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Load an engine from a file written by [save](Engine::save)
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SaveError> {
+        let file = io::BufReader::new(fs::File::open(path)?);
+        Ok(rmp_serde::from_read(file)?)
     }
 
     /// Run the main event loop
     pub fn run(this: Arc<Mutex<Self>>, sender: Sender<Event>, reciever: Receiver<Event>) {
-        let mut schedules = register::register_systems(); //Register all system functions
+        let schedules = register::register_systems(); //Register all system functions
+        Self::run_with(this, schedules, sender, reciever)
+    }
+
+    /// Run the event loop like [run](Engine::run), but with the given schedules instead of all registered
+    /// systems
+    ///
+    /// A panic in any system is caught and logged instead of stopping the event loop, but the [World] may
+    /// be left with only some of that schedule's changes applied
+    pub fn run_with(this: Arc<Mutex<Self>>, mut schedules: Schedules, sender: Sender<Event>, reciever: Receiver<Event>) {
         let mut resource = Resources::default();
-        resource.insert::<Sender<Event>>(sender.clone());
+        resource.insert(EventBus::new(sender.clone()));
+        resource.insert(Errors::default());
+        resource.insert(CollisionConfig::default());
         let sender = sender.clone();
         
         let exit = Arc::new(AtomicBool::new(false));
         let exit_rec = exit.clone();
         let handle = std::thread::spawn(move ||  {
-            loop {
-                std::thread::sleep(std::time::Duration::from_millis(100));
-                sender.send(Event::Tick).unwrap();
-                if exit_rec.load(atomic::Ordering::Relaxed) {
+            while !exit_rec.load(atomic::Ordering::Relaxed) {
+                std::thread::sleep(TICK_INTERVAL);
+                //Stop ticking if the event loop has stopped recieving events
+                if sender.send(Event::Tick).is_err() {
                     break
                 }
             }
-        });        
+        });
 
         loop {
-            match reciever.recv().unwrap() {
-                Event::Tick => schedules.tick.execute(&mut this.lock().world, &mut resource),
-                Event::Exit => break
+            let event = reciever.recv().unwrap();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| this.lock().process(&event, &mut schedules, &mut resource)));
+            match result {
+                Ok(Ok(())) => (),
+                Ok(Err(e)) => eprintln!("Failed to autosave: {}", e),
+                Err(_) => eprintln!("A system panicked while handling event {:?}, continuing", event),
+            }
+            for (system, e) in resource.get::<Errors>().map(|errors| errors.drain()).unwrap_or_default() {
+                eprintln!("System {} failed: {}", system, e);
+            }
+            if let Event::Exit = event {
+                break;
             }
         }
+        //Stop the tick thread and wait for it to finish before returning
         exit.store(true, atomic::Ordering::Relaxed);
-        handle.join().unwrap();
+        if handle.join().is_err() {
+            eprintln!("The tick thread panicked");
+        }
+    }
+}
+
+/// The size and contents of an [Engine], returned by [stats](Engine::stats)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EngineStats {
+    /// The number of entities in the world
+    pub entities: usize,
+    /// The number of different component types that at least one entity has
+    pub component_types: usize,
+    /// The number of star systems in the galaxy
+    pub systems: usize,
+    /// The size in bytes of the save file that would be written if the engine was saved now
+    pub save_size: usize,
+}
+
+/// A writer that only counts the bytes written to it
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The `EngineHandle` struct is a shared handle to an [Engine] and the channel that its event loop recieves
+/// events from, which can be cloned and sent between threads. Every method locks the engine internally, so
+/// callers never hold the lock longer than one call
+#[derive(Clone, Debug)]
+pub struct EngineHandle {
+    /// The engine shared by every handle
+    engine: Arc<Mutex<Engine>>,
+    /// A sender for events to the engine's event loop
+    sender: Sender<Event>,
+}
+
+impl EngineHandle {
+    /// Create a handle to the given engine, returning the handle and the reciever of every event sent
+    /// with [send_event](EngineHandle::send_event), which is passed to [spawn](EngineHandle::spawn)
+    pub fn new(engine: Engine) -> (Self, Receiver<Event>) {
+        let (sender, reciever) = mpsc::channel();
+        let handle = Self {
+            engine: Arc::new(Mutex::new(engine)),
+            sender,
+        };
+        (handle, reciever)
+    }
+
+    /// Run the engine's event loop with all registered systems on a new thread, handling events from the
+    /// reciever returned by [new](EngineHandle::new) until an [Exit](Event::Exit) event is sent
+    pub fn spawn(&self, reciever: Receiver<Event>) -> JoinHandle<()> {
+        let (engine, sender) = (self.engine.clone(), self.sender.clone());
+        std::thread::spawn(move || Engine::run(engine, sender, reciever))
+    }
+
+    /// Answer a [Query] directly from the engine, without waiting for the event loop
+    pub fn query(&self, req: Query) -> Response {
+        self.engine.lock().query(req)
+    }
+
+    /// Send an event to the engine's event loop, returning the event in an error if the event loop has stopped
+    pub fn send_event(&self, event: Event) -> Result<(), mpsc::SendError<Event>> {
+        self.sender.send(event)
+    }
+
+    /// Take a snapshot of every star system to compare incremental saves against, like [Engine::snapshot]
+    pub fn snapshot(&self) -> Result<EngineSnapshot, rmp_serde::encode::Error> {
+        self.engine.lock().snapshot()
+    }
+
+    /// Run a function with the engine locked, returning the function's result
+    pub fn with<R>(&self, f: impl FnOnce(&mut Engine) -> R) -> R {
+        f(&mut self.engine.lock())
+    }
+
+    /// Get a sender for events to the engine's event loop
+    #[inline(always)]
+    pub fn sender(&self) -> &Sender<Event> {
+        &self.sender
+    }
+}
+
+/// An error that can occur when saving an [Engine] to or loading an [Engine] from a file
+#[derive(Debug)]
+pub enum SaveError {
+    /// Reading or writing the save file failed
+    Io(io::Error),
+    /// Serializing the engine failed
+    Encode(rmp_serde::encode::Error),
+    /// The save file could not be deserialized
+    Decode(rmp_serde::decode::Error),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Failed to access save file: {}", e),
+            Self::Encode(e) => write!(f, "Failed to serialize engine: {}", e),
+            Self::Decode(e) => write!(f, "Failed to deserialize engine: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<rmp_serde::encode::Error> for SaveError {
+    fn from(e: rmp_serde::encode::Error) -> Self {
+        Self::Encode(e)
+    }
+}
+
+impl From<rmp_serde::decode::Error> for SaveError {
+    fn from(e: rmp_serde::decode::Error) -> Self {
+        Self::Decode(e)
+    }
+}
+
+/// An error that can occur when [replaying](Engine::replay) an [EventLog]
+#[derive(Debug)]
+pub enum ReplayError {
+    /// Loading the base save or autosaving during the replay failed
+    Save(SaveError),
+    /// An event was reached on a different tick than it was recorded on
+    Diverged {
+        /// The number of ticks run before the event when it was recorded
+        recorded: u64,
+        /// The number of ticks run before the event in the replay
+        replayed: u64,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Save(e) => write!(f, "{}", e),
+            Self::Diverged { recorded, replayed } => write!(
+                f,
+                "Replay diverged from the recorded session: an event recorded on tick {} was replayed on tick {}",
+                recorded, replayed
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<SaveError> for ReplayError {
+    fn from(e: SaveError) -> Self {
+        Self::Save(e)
+    }
+}
+
+/// A record of the serialized state of every star system in an [Engine] at one point in time, used as
+/// the base that incremental saves are compared against
+#[derive(Clone, Debug)]
+pub struct EngineSnapshot {
+    /// The serialized data of every star system, keyed by name
+    systems: IndexMap<String, Vec<u8>>,
+}
+
+/// The changes made to an [Engine] since an [EngineSnapshot] was taken, written by
+/// [save_delta](Engine::save_delta)
+#[derive(Deserialize, Serialize)]
+struct EngineDelta {
+    /// The serialized world, which is always saved in full
+    world: Vec<u8>,
+    /// The bounds of the galaxy
+    bounds: Rect,
+    /// The names of every star system in order, with the serialized star system if it was added or
+    /// changed since the snapshot, and `None` if it is unchanged. Star systems that were removed are
+    /// left out
+    systems: Vec<(String, Option<Vec<u8>>)>,
+    /// The random number generator shared by all systems
+    rng: GameRng,
+    /// The simulated time that the engine has been running
+    clock: SimClock,
+}
+
+impl Engine {
+    /// Serialize a star system using the canon names of this engine's entities
+    fn serialize_system(&self, system: &StarSystem) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+        set_entity_serializer(&self.canon, || rmp_serde::to_vec(system))
+    }
+
+    /// Take a snapshot of every star system to compare incremental saves against
+    pub fn snapshot(&self) -> Result<EngineSnapshot, rmp_serde::encode::Error> {
+        let systems = self
+            .state
+            .galaxy()
+            .systems()
+            .map(|(name, system)| Ok((name.to_owned(), self.serialize_system(system)?)))
+            .collect::<Result<_, rmp_serde::encode::Error>>()?;
+        Ok(EngineSnapshot { systems })
+    }
+
+    /// Write only the changes to the galaxy made since `base` was taken, along with the full world.
+    /// The engine can be rebuilt from the same snapshot and the written changes using
+    /// [apply_delta](Engine::apply_delta)
+    pub fn save_delta(&self, base: &EngineSnapshot, mut w: impl Write) -> Result<(), rmp_serde::encode::Error> {
+        let registry = register::register_components();
+        let world = rmp_serde::to_vec(&self.world.as_serializable(legion::any(), &registry, &self.canon))?;
+        let systems = self
+            .state
+            .galaxy()
+            .systems()
+            .map(|(name, system)| {
+                let serialized = self.serialize_system(system)?;
+                Ok(match base.systems.get(name) {
+                    Some(old) if *old == serialized => (name.to_owned(), None),
+                    _ => (name.to_owned(), Some(serialized)),
+                })
+            })
+            .collect::<Result<_, rmp_serde::encode::Error>>()?;
+
+        let delta = EngineDelta {
+            world,
+            bounds: self.state.galaxy().bounds(),
+            systems,
+            rng: self.rng.clone(),
+            clock: self.clock,
+        };
+        rmp_serde::encode::write(&mut w, &delta)
+    }
+
+    /// Rebuild an [Engine] from the snapshot that was used as the base of an incremental save and the
+    /// changes written by [save_delta](Engine::save_delta)
+    pub fn apply_delta(base: &EngineSnapshot, r: impl Read) -> Result<Self, rmp_serde::decode::Error> {
+        use rmp_serde::decode::Error;
+        use serde::de::Error as _;
+
+        let delta: EngineDelta = rmp_serde::from_read(r)?;
+        let registry = register::register_components();
+        let canon = Canon::default();
+        let world = registry
+            .as_deserialize(&canon)
+            .deserialize(&mut rmp_serde::Deserializer::from_read_ref(&delta.world))?;
+
+        let mut galaxy = Galaxy::with_bounds(delta.bounds);
+        for (name, changed) in delta.systems {
+            let serialized = match changed {
+                Some(ref serialized) => serialized,
+                None => base
+                    .systems
+                    .get(&name)
+                    .ok_or_else(|| Error::custom(format!("Star system {} is not in the base snapshot", name)))?,
+            };
+            let system: StarSystem = set_entity_serializer(&canon, || rmp_serde::from_read_ref(serialized))?;
+            galaxy
+                .add_system(name.clone(), system.pos(), system)
+                .map_err(|e| Error::custom(format!("Failed to add star system {}: {}", name, e)))?;
+        }
+
+        Ok(Self {
+            world,
+            state: State::new(galaxy),
+            rng: delta.rng,
+            clock: delta.clock,
+            canon,
+            log: None,
+        })
     }
 }
 
@@ -73,15 +621,19 @@ impl Serialize for Engine {
         S: Serializer,
     {
         let registry = register::register_components();
-        let entity_serializer = Canon::default();
         let serializable_world =
             self.world
-                .as_serializable(legion::any(), &registry, &entity_serializer);
+                .as_serializable(legion::any(), &registry, &self.canon);
 
-        let mut state = serializer.serialize_struct("Engine", 1)?;
-        state.serialize_field("world", &serializable_world)?;
-        state.serialize_field("state", &self.state)?;
-        state.end()
+        //Entities stored in the global state must be serialized with the same names as the world's entities
+        set_entity_serializer(&self.canon, || {
+            let mut state = serializer.serialize_struct("Engine", 4)?;
+            state.serialize_field("world", &serializable_world)?;
+            state.serialize_field("state", &self.state)?;
+            state.serialize_field("rng", &self.rng)?;
+            state.serialize_field("clock", &self.clock)?;
+            state.end()
+        })
     }
 }
 
@@ -91,12 +643,14 @@ impl<'de> Deserialize<'de> for Engine {
     where
         D: serde::Deserializer<'de>,
     {
-        const FIELDS: &[&str] = &["world", "state"];
+        const FIELDS: &[&str] = &["world", "state", "rng", "clock"];
 
         //Deserialize keys in a key-value map
         enum Field {
             World,
             State,
+            Rng,
+            Clock,
         }
         impl<'de> Deserialize<'de> for Field {
             fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -107,7 +661,7 @@ impl<'de> Deserialize<'de> for Engine {
                 impl<'de> serde::de::Visitor<'de> for FieldVisitor {
                     type Value = Field;
                     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-                        formatter.write_str("`world`, `state`")
+                        formatter.write_str("`world`, `state`, `rng`, `clock`")
                     }
 
                     fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
@@ -117,6 +671,8 @@ impl<'de> Deserialize<'de> for Engine {
                         match v {
                             "world" => Ok(Field::World),
                             "state" => Ok(Field::State),
+                            "rng" => Ok(Field::Rng),
+                            "clock" => Ok(Field::Clock),
                             _ => Err(serde::de::Error::unknown_field(v, FIELDS)),
                         }
                     }
@@ -139,18 +695,25 @@ impl<'de> Deserialize<'de> for Engine {
                 A: serde::de::SeqAccess<'de>,
             {
                 let registry = register::register_components();
-                let entity_deserializer = Canon::default();
-                let deserializable = registry.as_deserialize(&entity_deserializer);
+                let canon = Canon::default();
+                let deserializable = registry.as_deserialize(&canon);
                 let world = seq
                     .next_element_seed(deserializable)?
                     .ok_or_else(|| serde::de::Error::invalid_length(0, &self))?;
-                let state = seq
-                    .next_element()?
-                    .ok_or_else(|| serde::de::Error::invalid_length(0, &self))?;
+                let state = set_entity_serializer(&canon, || seq.next_element())?
+                    .ok_or_else(|| serde::de::Error::invalid_length(1, &self))?;
+                //Saves from before the random number generator and clock were added start them from their
+                //defaults
+                let rng = seq.next_element()?.unwrap_or_default();
+                let clock = seq.next_element()?.unwrap_or_default();
 
                 Ok(Engine {
                     world,
                     state,
+                    rng,
+                    clock,
+                    canon,
+                    log: None,
                 })
             }
 
@@ -159,8 +722,11 @@ impl<'de> Deserialize<'de> for Engine {
             where
                 A: serde::de::MapAccess<'de>,
             {
+                let canon = Canon::default();
                 let mut world = None;
                 let mut state = None;
+                let mut rng = None;
+                let mut clock = None;
 
                 while let Some(key) = map.next_key()? {
                     match key {
@@ -169,28 +735,451 @@ impl<'de> Deserialize<'de> for Engine {
                                 return Err(serde::de::Error::duplicate_field("world"));
                             }
                             let registry = register::register_components();
-                            let entity_deserializer = Canon::default();
-                            let deserializable = registry.as_deserialize(&entity_deserializer);
+                            let deserializable = registry.as_deserialize(&canon);
                             world = Some(map.next_value_seed(deserializable)?);
                         }
                         Field::State => {
                             if state.is_some() {
                                 return Err(serde::de::Error::duplicate_field("state"));
                             }
-                            state = Some(map.next_value()?);
+                            state = Some(set_entity_serializer(&canon, || map.next_value())?);
+                        }
+                        Field::Rng => {
+                            if rng.is_some() {
+                                return Err(serde::de::Error::duplicate_field("rng"));
+                            }
+                            rng = Some(map.next_value()?);
+                        }
+                        Field::Clock => {
+                            if clock.is_some() {
+                                return Err(serde::de::Error::duplicate_field("clock"));
+                            }
+                            clock = Some(map.next_value()?);
                         }
                     }
                 }
                 let world = world.ok_or_else(|| serde::de::Error::missing_field("world"))?;
                 let state = state.ok_or_else(|| serde::de::Error::missing_field("state"))?;
+                let rng = rng.unwrap_or_default();
+                let clock = clock.unwrap_or_default();
 
                 Ok(Engine {
                     world,
                     state,
+                    rng,
+                    clock,
+                    canon,
+                    log: None,
                 })
             }
         }
 
-        deserializer.deserialize_struct("Engine", &["world", "state"], EngineVisitor)
+        deserializer.deserialize_struct("Engine", FIELDS, EngineVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{component::misc::Name, state::Point};
+    use legion::{systems::CommandBuffer, EntityStore};
+    use rand::RngCore;
+
+    /// All values drawn from the [GameRng] by the test system
+    #[derive(Default)]
+    struct Draws(Vec<u64>);
+
+    #[legion::system]
+    fn draw(#[resource] rng: &mut GameRng, #[resource] draws: &mut Draws) {
+        draws.0.push(rng.next_u64());
+    }
+
+    /// Spawn a ship with a randomly numbered name every tick
+    #[legion::system]
+    fn spawn_ship(cmd: &mut CommandBuffer, #[resource] rng: &mut GameRng) {
+        cmd.push((Name { name: format!("Ship {}", rng.next_u32()) },));
+    }
+
+    /// An [Engine] serialized with the layout saved before the random number generator and clock were added
+    struct EngineV1<'a>(&'a Engine);
+
+    impl Serialize for EngineV1<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let registry = register::register_components();
+            let world = self.0.world.as_serializable(legion::any(), &registry, &self.0.canon);
+            set_entity_serializer(&self.0.canon, || {
+                let mut state = serializer.serialize_struct("Engine", 2)?;
+                state.serialize_field("world", &world)?;
+                state.serialize_field("state", &self.0.state)?;
+                state.end()
+            })
+        }
+    }
+
+    /// Run the draw system on the engine for the given number of ticks, returning all drawn values
+    fn run_ticks(engine: &mut Engine, ticks: usize) -> Vec<u64> {
+        let mut schedule = Schedule::builder().add_system(draw_system()).build();
+        let mut resources = Resources::default();
+        resources.insert(Draws::default());
+        for _ in 0..ticks {
+            engine.execute(&mut schedule, &mut resources);
+        }
+        let draws = resources.remove::<Draws>().unwrap();
+        draws.0
+    }
+
+    /// Get the name, position, and number of entities of every star system in the engine
+    fn galaxy_summary(engine: &Engine) -> Vec<(String, Point, usize)> {
+        engine
+            .state()
+            .galaxy()
+            .systems()
+            .map(|(name, system)| (name.to_owned(), system.pos(), system.entities().count()))
+            .collect()
+    }
+
+    #[test]
+    pub fn test_delta() {
+        let mut engine = Engine::new_seeded(0);
+        let bounds = Rect::new(Point(0., 0.), Point(100., 100.));
+        for (name, pos) in [("Sol", Point(10., 10.)), ("Sirius", Point(50., 50.)), ("Vega", Point(90., 90.))] {
+            let mut system = StarSystem::new(bounds);
+            let entity = engine.world_mut().push((Name { name: name.to_owned() },));
+            system.insert_entity(entity, Point(5., 5.)).unwrap();
+            engine.state_mut().galaxy_mut().add_system(name, pos, system).unwrap();
+        }
+        let base = engine.snapshot().unwrap();
+
+        //Remove one system, move another, add a new system, and add entities to the world
+        engine.state_mut().galaxy_mut().remove_system("Sirius").unwrap();
+        let vega = engine.state_mut().galaxy_mut().remove_system("Vega").unwrap();
+        engine.state_mut().galaxy_mut().add_system("Vega", Point(70., 20.), vega).unwrap();
+        let mut system = StarSystem::new(bounds);
+        let entity = engine.world_mut().push((Name { name: "Polaris".to_owned() },));
+        system.insert_entity(entity, Point(1., 1.)).unwrap();
+        engine.state_mut().galaxy_mut().add_system("Polaris", Point(30., 80.), system).unwrap();
+
+        let mut delta = Vec::new();
+        engine.save_delta(&base, &mut delta).unwrap();
+        let full = rmp_serde::to_vec(&engine).unwrap();
+        assert!(delta.len() < full.len());
+        let reloaded: Engine = rmp_serde::from_read_ref(&full).unwrap();
+        assert_eq!(galaxy_summary(&reloaded), galaxy_summary(&engine));
+
+        let loaded = Engine::apply_delta(&base, delta.as_slice()).unwrap();
+        assert_eq!(galaxy_summary(&loaded), galaxy_summary(&engine));
+        assert_eq!(loaded.world().len(), engine.world().len());
+
+        //Entities in star systems refer to the loaded world's entities
+        for (_, system) in loaded.state().galaxy().systems() {
+            for (_, entity) in system.entities() {
+                assert!(loaded.world().entry_ref(entity).unwrap().get_component::<Name>().is_ok());
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_autosave() {
+        let path = std::env::temp_dir().join(format!("starfleet-autosave-{}.sav", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut engine = Engine::new_seeded(0);
+        engine.world_mut().push((Name { name: "Enterprise".to_owned() },));
+        let mut schedules = Schedules {
+            tick: Schedule::builder().build(),
+        };
+        let mut resources = Resources::default();
+        resources.insert(AutosaveConfig::new(&path, TICK_INTERVAL * 3));
+
+        engine.tick(&mut schedules, &mut resources).unwrap();
+        engine.tick(&mut schedules, &mut resources).unwrap();
+        assert!(!path.exists());
+        engine.tick(&mut schedules, &mut resources).unwrap();
+        assert!(path.exists());
+
+        let loaded = Engine::load(&path).unwrap();
+        assert_eq!(loaded.world().len(), 1);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    pub fn test_stats() {
+        let mut engine = Engine::new_seeded(0);
+        let stats = engine.stats().unwrap();
+        assert_eq!((stats.entities, stats.component_types, stats.systems), (0, 0, 0));
+
+        engine.world_mut().push((Name { name: "Station".to_owned() },));
+        engine.world_mut().push((Name { name: "Ship".to_owned() }, crate::component::misc::Location { loc: Point(1., 2.) }));
+        engine.world_mut().push((Name { name: "Probe".to_owned() },));
+        for (name, pos) in [("Sol", Point(10., 10.)), ("Vega", Point(90., 90.))] {
+            let system = StarSystem::new(Rect::new(Point(0., 0.), Point(100., 100.)));
+            engine.state_mut().galaxy_mut().add_system(name, pos, system).unwrap();
+        }
+
+        let populated = engine.stats().unwrap();
+        assert_eq!((populated.entities, populated.component_types, populated.systems), (3, 2, 2));
+        assert_eq!(populated.save_size, rmp_serde::to_vec(&engine).unwrap().len());
+        assert!(populated.save_size > stats.save_size);
+    }
+
+    #[test]
+    pub fn test_handle() {
+        fn assert_shared<T: Clone + Send + Sync>() {}
+        assert_shared::<EngineHandle>();
+
+        let (handle, reciever) = EngineHandle::new(Engine::new_seeded(0));
+        let threads = (0..2)
+            .map(|i| {
+                let handle = handle.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..10 {
+                        handle.with(|engine| engine.world_mut().push((Name { name: format!("Probe {}", i) },)));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(handle.query(Query::EntityCount), Response::EntityCount(20));
+        assert!(handle.snapshot().unwrap().systems.is_empty());
+
+        let event_loop = handle.spawn(reciever);
+        assert_eq!(crate::event::query(handle.sender(), Query::EntityCount), Some(Response::EntityCount(20)));
+        handle.send_event(Event::Exit).unwrap();
+        event_loop.join().unwrap();
+    }
+
+    /// A component that always fails to serialize, used to interrupt a save partway through
+    #[crate::component]
+    #[derive(Debug, Deserialize)]
+    struct Unserializable;
+
+    impl Serialize for Unserializable {
+        fn serialize<S: Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+            Err(serde::ser::Error::custom("Unserializable component"))
+        }
+    }
+
+    #[test]
+    pub fn test_interrupted_save() {
+        let path = std::env::temp_dir().join(format!("starfleet-interrupted-{}.sav", std::process::id()));
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+
+        let mut engine = Engine::new_seeded(0);
+        engine.world_mut().push((Name { name: "Enterprise".to_owned() },));
+        engine.save(&path).unwrap();
+
+        engine.world_mut().push((Name { name: "Defiant".to_owned() }, Unserializable));
+        assert!(matches!(engine.save(&path), Err(SaveError::Encode(_))));
+        assert!(!Path::new(&tmp_path).exists());
+
+        //The save from before the failed save is untouched
+        let loaded = Engine::load(&path).unwrap();
+        assert_eq!(loaded.world().len(), 1);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    pub fn test_query() {
+        let mut engine = Engine::new_seeded(0);
+        for _ in 0..3 {
+            engine.world_mut().push((Name { name: "Probe".to_owned() },));
+        }
+        let engine = Arc::new(Mutex::new(engine));
+        let (sender, reciever) = std::sync::mpsc::channel();
+        let handle = {
+            let (engine, sender) = (engine.clone(), sender.clone());
+            std::thread::spawn(move || Engine::run(engine, sender, reciever))
+        };
+
+        assert_eq!(crate::event::query(&sender, Query::EntityCount), Some(Response::EntityCount(3)));
+        assert_eq!(crate::event::query(&sender, Query::SystemCount), Some(Response::SystemCount(0)));
+        sender.send(Event::Exit).unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    pub fn test_dump() {
+        use crate::component::misc::Location;
+
+        let mut engine = Engine::new_seeded(0);
+        engine.world_mut().push((Name { name: "Station".to_owned() },));
+        engine.world_mut().push((Name { name: "Ship".to_owned() }, Location { loc: Point(1.5, 2.) }));
+
+        let dump = engine.dump();
+        assert_eq!(dump.lines().filter(|line| line.starts_with("Entity")).count(), 2, "{}", dump);
+        assert!(dump.contains(r#"Name: Name { name: "Station" }"#), "{}", dump);
+        assert!(dump.contains(r#"Name: Name { name: "Ship" }"#), "{}", dump);
+        assert!(dump.contains("Location: Location { loc: Point(1.5, 2.0) }"), "{}", dump);
+        assert!(Engine::new_seeded(0).dump().is_empty());
+    }
+
+    /// The number of times the panicking test system has run
+    static PANIC_TICKS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+    #[legion::system]
+    fn panic_once() {
+        if PANIC_TICKS.fetch_add(1, atomic::Ordering::SeqCst) == 0 {
+            panic!("Test system panicked");
+        }
+    }
+
+    #[test]
+    pub fn test_panic_boundary() {
+        let mut engine = Engine::new_seeded(0);
+        engine.world_mut().push((Name { name: "Probe".to_owned() },));
+        let engine = Arc::new(Mutex::new(engine));
+        let (sender, reciever) = std::sync::mpsc::channel();
+        let handle = {
+            let (engine, sender) = (engine.clone(), sender.clone());
+            std::thread::spawn(move || {
+                let schedules = Schedules { tick: Schedule::builder().add_system(panic_once_system()).build() };
+                Engine::run_with(engine, schedules, sender, reciever)
+            })
+        };
+
+        //Wait for the engine to keep ticking after the first tick panicked
+        let start = std::time::Instant::now();
+        while PANIC_TICKS.load(atomic::Ordering::SeqCst) < 3 {
+            assert!(start.elapsed() < Duration::from_secs(10), "Engine stopped ticking after a panic");
+            std::thread::sleep(TICK_INTERVAL);
+        }
+        assert_eq!(crate::event::query(&sender, Query::EntityCount), Some(Response::EntityCount(1)));
+        sender.send(Event::Exit).unwrap();
+        handle.join().unwrap();
+    }
+
+    #[legion::system]
+    fn raise_exit(#[resource] events: &EventBus) {
+        events.raise(Event::Exit);
+    }
+
+    #[test]
+    pub fn test_event_bus() {
+        let engine = Arc::new(Mutex::new(Engine::new_seeded(0)));
+        let (sender, reciever) = std::sync::mpsc::channel();
+        let (done_sender, done) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let schedules = Schedules { tick: Schedule::builder().add_system(raise_exit_system()).build() };
+            Engine::run_with(engine, schedules, sender, reciever);
+            done_sender.send(()).unwrap();
+        });
+        //The engine only stops if it handles the exit event raised by the system on the first tick
+        assert!(done.recv_timeout(Duration::from_secs(10)).is_ok(), "Engine did not handle the raised event");
+    }
+
+    #[test]
+    pub fn test_shutdown() {
+        let engine = Arc::new(Mutex::new(Engine::new_seeded(0)));
+        let (sender, reciever) = std::sync::mpsc::channel();
+        let (done_sender, done) = std::sync::mpsc::channel();
+        {
+            let engine = engine.clone();
+            let sender = sender.clone();
+            std::thread::spawn(move || {
+                Engine::run_with(engine, Schedules { tick: Schedule::builder().build() }, sender, reciever);
+                done_sender.send(()).unwrap();
+            });
+        }
+        std::thread::sleep(TICK_INTERVAL * 3);
+        sender.send(Event::Exit).unwrap();
+
+        assert!(done.recv_timeout(Duration::from_secs(2)).is_ok(), "Engine did not shut down");
+        //Nothing is recieving events after shutdown
+        assert!(sender.send(Event::Tick).is_err());
+    }
+
+    #[test]
+    pub fn test_sim_clock() {
+        let path = std::env::temp_dir().join(format!("starfleet-clock-{}.sav", std::process::id()));
+        let mut engine = Engine::new_seeded(0);
+        let mut schedules = Schedules {
+            tick: Schedule::builder().build(),
+        };
+        let mut resources = Resources::default();
+        assert_eq!(engine.sim_clock(), SimClock::default());
+        for ticks in 1..=5 {
+            engine.tick(&mut schedules, &mut resources).unwrap();
+            assert_eq!(engine.sim_clock().ticks, ticks);
+            assert_eq!(engine.sim_clock().elapsed, TICK_INTERVAL * ticks as u32);
+        }
+
+        engine.save(&path).unwrap();
+        let mut loaded = Engine::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.sim_clock(), engine.sim_clock());
+        loaded.tick(&mut schedules, &mut resources).unwrap();
+        assert_eq!(loaded.sim_clock().ticks, 6);
+        assert_eq!(loaded.sim_clock().elapsed, TICK_INTERVAL * 6);
+    }
+
+    #[test]
+    pub fn test_seeded_rng() {
+        let mut first = Engine::new_seeded(1234);
+        let mut second = Engine::new_seeded(1234);
+        let draws = run_ticks(&mut first, 4);
+        assert_eq!(draws.len(), 4);
+        assert_eq!(draws, run_ticks(&mut second, 4));
+
+        //Reloading the engine resumes the same stream of random values
+        let saved = rmp_serde::to_vec(&first).unwrap();
+        let mut loaded: Engine = rmp_serde::from_read_ref(&saved).unwrap();
+        assert_eq!(run_ticks(&mut loaded, 3), run_ticks(&mut second, 3));
+    }
+
+    #[test]
+    pub fn test_replay() {
+        let path = std::env::temp_dir().join(format!("starfleet-replay-{}.sav", std::process::id()));
+        let schedules = || Schedules {
+            tick: Schedule::builder().add_system(spawn_ship_system()).build(),
+        };
+        let mut recorded = schedules();
+        let mut resources = Resources::default();
+        let mut engine = Engine::new_seeded(99);
+        engine.world_mut().push((Name { name: "Enterprise".to_owned() },));
+        for _ in 0..2 {
+            engine.process(&Event::Tick, &mut recorded, &mut resources).unwrap();
+        }
+
+        engine.save(&path).unwrap();
+        engine.start_recording();
+        let (reply, response) = mpsc::channel();
+        let events = vec![Event::Tick, Event::Tick, Event::Query { req: Query::EntityCount, reply }, Event::Tick];
+        for event in &events {
+            engine.process(event, &mut recorded, &mut resources).unwrap();
+        }
+        assert_eq!(response.try_recv(), Ok(Response::EntityCount(5)));
+        let log = engine.stop_recording().unwrap();
+        assert!(engine.stop_recording().is_none());
+        //Queries don't change the engine, so they are never replayed
+        assert_eq!(log.events().iter().map(|(tick, _)| *tick).collect::<Vec<_>>(), vec![2, 3, 4]);
+
+        let replayed = Engine::replay(&log, &path, &mut schedules(), &mut Resources::default()).unwrap();
+        assert_eq!(replayed.sim_clock(), engine.sim_clock());
+        assert_eq!(replayed.stats().unwrap(), engine.stats().unwrap());
+        assert_eq!(replayed.stats().unwrap().entities, 6);
+        assert!(response.try_recv().is_err());
+
+        //Replaying from a save taken after recording started must fail instead of producing another session
+        engine.save(&path).unwrap();
+        let diverged = Engine::replay(&log, &path, &mut schedules(), &mut Resources::default());
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(diverged, Err(ReplayError::Diverged { recorded: 2, replayed: 5 })));
+    }
+
+    #[test]
+    pub fn test_load_without_rng() {
+        let mut engine = Engine::new_seeded(5);
+        engine.world_mut().push((Name { name: "Enterprise".to_owned() },));
+        for saved in [rmp_serde::to_vec(&EngineV1(&engine)), rmp_serde::to_vec_named(&EngineV1(&engine))] {
+            let mut loaded: Engine = rmp_serde::from_read_ref(&saved.unwrap()).unwrap();
+            assert_eq!(loaded.world().len(), 1);
+            assert_eq!(loaded.sim_clock(), SimClock::default());
+            //A missing random number generator is seeded like the default one
+            let mut default = Engine::new_seeded(0);
+            assert_eq!(run_ticks(&mut loaded, 3), run_ticks(&mut default, 3));
+        }
     }
 }
@@ -1,5 +1,8 @@
 //! The `event` module provides definitions for all events that can be raised
 //! by systems, and the additional state (if any) that is sent with the event
+use std::sync::mpsc::{self, Sender};
+
+use legion::Entity;
 
 /// The `Event` enum is the type that all events are converted to so they can be sent
 #[derive(Debug, Clone)]
@@ -8,4 +11,104 @@ pub enum Event {
     Exit,
     /// Fired once every tenth of a second
     Tick,
+    /// Fired when two entities in the same star system overlap
+    Collision {
+        /// The first entity in the collision
+        a: Entity,
+        /// The second entity in the collision
+        b: Entity,
+    },
+    /// Fired when an entity's location was outside of its star system's bounds and was clamped to the
+    /// edge of the star system
+    OutOfBounds {
+        /// The entity that left the star system's bounds
+        entity: Entity,
+    },
+    /// Fired when another thread wants data from the engine, which the engine answers by sending a
+    /// [Response] to `reply`
+    Query {
+        /// The data being requested
+        req: Query,
+        /// The channel the engine sends its response to
+        reply: Sender<Response>,
+    },
+}
+
+/// The `EventBus` resource lets systems raise events that the [Engine](crate::Engine) handles after
+/// the current schedule finishes running
+#[derive(Debug, Clone)]
+pub struct EventBus(Sender<Event>);
+
+impl EventBus {
+    /// Create a new event bus sending events to the given channel
+    pub fn new(sender: Sender<Event>) -> Self {
+        Self(sender)
+    }
+
+    /// Raise an event, logging an error instead of panicking if the engine is no longer recieving events
+    pub fn raise(&self, event: Event) {
+        if let Err(e) = self.0.send(event) {
+            eprintln!("Failed to raise event {:?}: the engine is no longer recieving events", e.0);
+        }
+    }
+}
+
+/// A record of every event processed by an [Engine](crate::Engine) and the tick it was processed on, which
+/// can be [replayed](crate::Engine::replay) from a save of the engine taken when recording started to
+/// reproduce the session exactly
+#[derive(Debug, Clone, Default)]
+pub struct EventLog {
+    /// Every event in the order it was processed, with the number of ticks run before it was processed
+    events: Vec<(u64, Event)>,
+}
+
+impl EventLog {
+    /// Add an event processed after the given number of ticks to the end of the log
+    pub fn record(&mut self, tick: u64, event: Event) {
+        self.events.push((tick, event));
+    }
+
+    /// Get every event in the order it was processed, with the number of ticks run before it was processed
+    #[inline]
+    pub fn events(&self) -> &[(u64, Event)] {
+        &self.events
+    }
+
+    /// Get the number of events in the log
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Check if no events have been recorded
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+/// Data that can be requested from the engine with a [Query](Event::Query) event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Query {
+    /// The number of entities in the world
+    EntityCount,
+    /// The number of star systems in the galaxy
+    SystemCount,
+}
+
+/// The engine's response to a [Query]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    /// The number of entities in the world
+    EntityCount(usize),
+    /// The number of star systems in the galaxy
+    SystemCount(usize),
+}
+
+/// Send a [Query] to the engine and wait for its response, returning `None` if the engine is
+/// no longer running
+pub fn query(sender: &Sender<Event>, req: Query) -> Option<Response> {
+    let (reply, response) = mpsc::channel();
+    sender.send(Event::Query { req, reply }).ok()?;
+    response.recv().ok()
 }
@@ -0,0 +1,170 @@
+//! The `inspect` module reads which components are present in a save file without deserializing
+//! it into a [World](legion::World), for debugging corrupted saves
+use std::{collections::BTreeMap, fmt, io::Read};
+
+use serde::{
+    de::{IgnoredAny, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+use crate::register;
+
+/// A summary of the components stored in a save file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SaveReport {
+    /// The total number of entities in the save
+    pub entities: usize,
+    /// Every component type in the save, sorted by hash
+    pub components: Vec<ComponentReport>,
+}
+
+/// The number of entities that have a component in a save file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComponentReport {
+    /// The hash the component was serialized with
+    pub hash: u64,
+    /// The name of the component, or `None` if no registered component has this hash
+    pub name: Option<&'static str>,
+    /// The number of entities with this component
+    pub entities: usize,
+}
+
+impl SaveReport {
+    /// Get every component in the save that isn't registered in this build
+    pub fn unknown(&self) -> impl Iterator<Item = &ComponentReport> {
+        self.components.iter().filter(|component| component.name.is_none())
+    }
+}
+
+/// An error returned when a save file can't be parsed by [inspect_save]
+#[derive(Debug)]
+pub struct InspectErr(rmp_serde::decode::Error);
+
+impl fmt::Display for InspectErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to inspect save file: {}", self.0)
+    }
+}
+
+impl std::error::Error for InspectErr {}
+
+impl From<rmp_serde::decode::Error> for InspectErr {
+    fn from(e: rmp_serde::decode::Error) -> Self {
+        Self(e)
+    }
+}
+
+/// The fields of a saved [Engine](crate::Engine), ignoring everything but the world
+#[derive(Deserialize)]
+struct SavedEngine {
+    world: SavedWorld,
+    #[serde(rename = "state")]
+    _state: IgnoredAny,
+    #[serde(rename = "rng")]
+    _rng: IgnoredAny,
+    #[serde(rename = "clock", default)]
+    _clock: IgnoredAny,
+}
+
+/// Every archetype of a world serialized in legion's packed format
+struct SavedWorld(Vec<SavedArchetype>);
+
+/// An archetype in legion's packed format, only keeping the component hashes and number of entities
+#[derive(Deserialize)]
+struct SavedArchetype {
+    #[serde(rename = "_layout")]
+    layout: Vec<u64>,
+    entities: Vec<IgnoredAny>,
+    #[serde(rename = "components")]
+    _components: IgnoredAny,
+}
+
+impl<'de> Deserialize<'de> for SavedWorld {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct WorldVisitor;
+        impl<'de> Visitor<'de> for WorldVisitor {
+            type Value = SavedWorld;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a packed legion world")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                //The world is a map with a single entry naming the format, which is always packed for save files
+                match map.next_key::<IgnoredAny>()? {
+                    Some(_) => Ok(SavedWorld(map.next_value()?)),
+                    None => Ok(SavedWorld(Vec::new())),
+                }
+            }
+        }
+        deserializer.deserialize_map(WorldVisitor)
+    }
+}
+
+/// Read a save file written by [Engine::save](crate::Engine::save) and list every component hash in it,
+/// flagging any hashes that aren't registered components in this build
+pub fn inspect_save(r: impl Read) -> Result<SaveReport, InspectErr> {
+    let saved: SavedEngine = rmp_serde::from_read(r)?;
+    let names = register::registered_components().into_iter().collect::<BTreeMap<_, _>>();
+
+    let mut counts = BTreeMap::new();
+    let mut entities = 0;
+    for archetype in saved.world.0 {
+        entities += archetype.entities.len();
+        for hash in archetype.layout {
+            *counts.entry(hash).or_insert(0) += archetype.entities.len();
+        }
+    }
+
+    Ok(SaveReport {
+        entities,
+        components: counts
+            .into_iter()
+            .map(|(hash, entities)| ComponentReport {
+                hash,
+                name: names.get(&hash).copied(),
+                entities,
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        component::misc::{Location, Name},
+        state::Point,
+        Engine,
+    };
+
+    #[test]
+    pub fn test_inspect_save() {
+        let mut engine = Engine::new_seeded(0);
+        engine.world_mut().push((Name { name: "Station".to_owned() },));
+        engine.world_mut().push((Name { name: "Ship".to_owned() }, Location { loc: Point(1., 2.) }));
+        engine.world_mut().push((Location { loc: Point(3., 4.) },));
+        let save = rmp_serde::to_vec(&engine).unwrap();
+
+        let report = inspect_save(save.as_slice()).unwrap();
+        let hash_of = |name: &str| {
+            register::registered_components()
+                .into_iter()
+                .find(|(_, component)| *component == name)
+                .unwrap()
+                .0
+        };
+        let mut expected = vec![
+            ComponentReport { hash: hash_of("Name"), name: Some("Name"), entities: 2 },
+            ComponentReport { hash: hash_of("Location"), name: Some("Location"), entities: 2 },
+        ];
+        expected.sort_by_key(|component| component.hash);
+        assert_eq!(report, SaveReport { entities: 3, components: expected });
+        assert_eq!(report.unknown().count(), 0);
+
+        assert!(inspect_save(&save[..save.len() / 2]).is_err());
+    }
+}
@@ -1,9 +1,11 @@
-pub use starfleet_derive::{component, on_event};
+pub use starfleet_derive::{component, fallible, on_event};
 pub mod component;
 pub mod engine;
 pub mod event;
 pub mod gen;
+pub mod inspect;
 pub mod register;
+pub mod resources;
 pub mod state;
 pub mod system;
 
@@ -1,11 +1,25 @@
 //! The `register` module provides platform-independent component and system registration for the `legion` crate
 use crate::engine::Schedules;
-use legion::serialize::Registry;
+use legion::{serialize::Registry, world::EntryRef};
+
+/// A function that formats the value of one component type on an entity with its [Debug](std::fmt::Debug)
+/// implementation, returning `None` if the entity doesn't have the component
+pub type ComponentFormatter = for<'a> fn(&EntryRef<'a>) -> Option<String>;
 
 #[cfg(use_linkme)]
 #[::linkme::distributed_slice]
 pub static COMPONENT_HASHES: [fn(&mut Registry<u64>)] = [..];
 
+/// The hash and type name of every registered component
+#[cfg(use_linkme)]
+#[::linkme::distributed_slice]
+pub static COMPONENT_NAMES: [(u64, &'static str)] = [..];
+
+/// The hash and formatter of every registered component
+#[cfg(use_linkme)]
+#[::linkme::distributed_slice]
+pub static COMPONENT_FORMATTERS: [(u64, ComponentFormatter)] = [..];
+
 #[cfg(use_linkme)]
 #[::linkme::distributed_slice]
 pub static SYSTEM_REGISTRARS: [fn(&mut SchedulesBuilder)] = [..];
@@ -35,6 +49,20 @@ pub struct RegistrarFunction(pub fn(&mut Registry<u64>));
 #[cfg(use_inventory)]
 ::inventory::collect!(RegistrarFunction);
 
+/// The hash and type name of a registered component
+#[cfg(use_inventory)]
+pub struct ComponentName(pub u64, pub &'static str);
+
+#[cfg(use_inventory)]
+::inventory::collect!(ComponentName);
+
+/// The hash and formatter of a registered component
+#[cfg(use_inventory)]
+pub struct ComponentFormatterFunction(pub u64, pub ComponentFormatter);
+
+#[cfg(use_inventory)]
+::inventory::collect!(ComponentFormatterFunction);
+
 /// Register all components using the `linkme` crate
 #[cfg(use_linkme)]
 pub fn register_components() -> Registry<u64> {
@@ -55,6 +83,44 @@ pub fn register_components() -> Registry<u64> {
     registry
 }
 
+/// Get the hash and type name of every registered component using the `linkme` crate, sorted by hash
+#[cfg(use_linkme)]
+pub fn registered_components() -> Vec<(u64, &'static str)> {
+    let mut components = COMPONENT_NAMES.to_vec();
+    components.sort_unstable();
+    components
+}
+
+/// Get the hash and type name of every registered component using the `inventory` crate, sorted by hash
+#[cfg(use_inventory)]
+pub fn registered_components() -> Vec<(u64, &'static str)> {
+    let mut components = inventory::iter::<ComponentName>
+        .into_iter()
+        .map(|ComponentName(hash, name)| (*hash, *name))
+        .collect::<Vec<_>>();
+    components.sort_unstable();
+    components
+}
+
+/// Get the hash and formatter of every registered component using the `linkme` crate, sorted by hash
+#[cfg(use_linkme)]
+pub fn component_formatters() -> Vec<(u64, ComponentFormatter)> {
+    let mut formatters = COMPONENT_FORMATTERS.to_vec();
+    formatters.sort_unstable_by_key(|(hash, _)| *hash);
+    formatters
+}
+
+/// Get the hash and formatter of every registered component using the `inventory` crate, sorted by hash
+#[cfg(use_inventory)]
+pub fn component_formatters() -> Vec<(u64, ComponentFormatter)> {
+    let mut formatters = inventory::iter::<ComponentFormatterFunction>
+        .into_iter()
+        .map(|ComponentFormatterFunction(hash, formatter)| (*hash, *formatter))
+        .collect::<Vec<_>>();
+    formatters.sort_unstable_by_key(|(hash, _)| *hash);
+    formatters
+}
+
 /// Register all systems using the `linkme` crate
 #[cfg(use_linkme)]
 pub fn register_systems() -> Schedules {
@@ -78,3 +144,74 @@ pub fn register_systems() -> Schedules {
     }
     schedules.build()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    /// A component registered under the same name as [SecondProbe] in a different namespace
+    #[crate::component(name = "Probe", namespace = "first")]
+    #[derive(Debug, Deserialize, Serialize)]
+    struct FirstProbe;
+
+    /// A component registered under the same name as [FirstProbe] in a different namespace
+    #[crate::component(name = "Probe", namespace = "second")]
+    #[derive(Debug, Deserialize, Serialize)]
+    struct SecondProbe;
+
+    /// A component as it was saved before its `health` field was renamed and the `regen` field was added
+    #[derive(Deserialize, Serialize)]
+    struct OldShield {
+        health: f32,
+    }
+
+    /// The current version of [OldShield], which must load from saves of the old version
+    #[crate::component(field_renames(hp = "health"), field_defaults(regen))]
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Shield {
+        hp: f32,
+        regen: f32,
+    }
+
+    /// Hash a component name with the fnv1a-64 algorithm used by the component macro
+    fn fnv1a(name: &str) -> u64 {
+        name.bytes()
+            .fold(14695981039346656037u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(1099511628211))
+    }
+
+    #[test]
+    pub fn test_registered_components() {
+        let components = registered_components();
+        assert!(components.contains(&(fnv1a("Name"), "Name")), "{:?}", components);
+        assert!(components.iter().any(|(_, name)| *name == "Location"));
+        assert!(components.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    pub fn test_namespaced_components() {
+        let components = registered_components();
+        assert!(components.contains(&(fnv1a("first::Probe"), "FirstProbe")), "{:?}", components);
+        assert!(components.contains(&(fnv1a("second::Probe"), "SecondProbe")), "{:?}", components);
+        assert!(!components.iter().any(|(hash, _)| *hash == fnv1a("Probe")));
+
+        let mut engine = crate::Engine::new_seeded(0);
+        engine.world_mut().push((FirstProbe, SecondProbe));
+        let dump = engine.dump();
+        assert!(dump.contains("FirstProbe: FirstProbe") && dump.contains("SecondProbe: SecondProbe"), "{}", dump);
+    }
+
+    #[test]
+    pub fn test_field_defaults() {
+        let old = OldShield { health: 40. };
+        let saved = rmp_serde::to_vec(&old).unwrap();
+        let loaded: Shield = rmp_serde::from_read_ref(&saved).unwrap();
+        assert_eq!(loaded, Shield { hp: 40., regen: 0. });
+
+        //Fields saved by name must be found under the name they were saved with
+        let saved = rmp_serde::to_vec_named(&old).unwrap();
+        let loaded: Shield = rmp_serde::from_read_ref(&saved).unwrap();
+        assert_eq!(loaded, Shield { hp: 40., regen: 0. });
+        assert_eq!(rmp_serde::to_vec_named(&loaded).unwrap().windows(6).filter(|name| *name == b"health").count(), 1);
+    }
+}
@@ -0,0 +1,128 @@
+//! The `resources` module provides types that are inserted into the legion [Resources](legion::Resources)
+//! when systems are run, and that persist with the [Engine](crate::Engine) between runs
+
+use std::{path::PathBuf, time::Duration};
+
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+/// The `GameRng` resource is a seeded random number generator shared by all systems, making
+/// any randomness in the simulation reproducible from the seed and resumable from a save file
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "GameRngState", into = "GameRngState")]
+pub struct GameRng(ChaCha8Rng);
+
+/// The serialized position of a [GameRng] in its stream of random values, with the `u128` word
+/// position split into two `u64`s because the save file format can't store 128 bit integers
+#[derive(Serialize, Deserialize)]
+struct GameRngState {
+    seed: [u8; 32],
+    stream: u64,
+    word_pos: [u64; 2],
+}
+
+impl From<GameRng> for GameRngState {
+    fn from(rng: GameRng) -> Self {
+        let word_pos = rng.0.get_word_pos();
+        Self {
+            seed: rng.0.get_seed(),
+            stream: rng.0.get_stream(),
+            word_pos: [(word_pos >> 64) as u64, word_pos as u64],
+        }
+    }
+}
+
+impl From<GameRngState> for GameRng {
+    fn from(state: GameRngState) -> Self {
+        let mut rng = ChaCha8Rng::from_seed(state.seed);
+        rng.set_stream(state.stream);
+        rng.set_word_pos(((state.word_pos[0] as u128) << 64) | state.word_pos[1] as u128);
+        Self(rng)
+    }
+}
+
+impl GameRng {
+    /// Create a new random number generator from the given seed
+    pub fn new(seed: u64) -> Self {
+        Self(ChaCha8Rng::seed_from_u64(seed))
+    }
+}
+
+impl Default for GameRng {
+    /// Create a random number generator with a seed of `0`
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl RngCore for GameRng {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    #[inline]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    #[inline]
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
+
+/// The `SimClock` resource tracks how long the simulation has been running, and is advanced by the
+/// [Engine](crate::Engine) after every tick
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SimClock {
+    /// The number of ticks that have been run
+    pub ticks: u64,
+    /// The simulated time that has passed
+    pub elapsed: Duration,
+}
+
+impl SimClock {
+    /// Advance the clock by one tick of the given length
+    pub fn advance(&mut self, tick: Duration) {
+        self.ticks += 1;
+        self.elapsed += tick;
+    }
+}
+
+/// The `CollisionConfig` resource controls how the collision system divides star systems into cells
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CollisionConfig {
+    /// The smallest size of the cells that bodies are sorted into. Cells are always at least as wide as
+    /// the largest body, and larger cells mean fewer cells but more bodies tested per cell
+    pub min_cell_size: f32,
+}
+
+/// The `AutosaveConfig` resource makes the [Engine](crate::Engine) save itself to a file every time
+/// a given amount of simulated time has passed
+#[derive(Clone, Debug)]
+pub struct AutosaveConfig {
+    /// The path of the save file to write
+    pub path: PathBuf,
+    /// The simulated time between autosaves
+    pub interval: Duration,
+    /// The simulated time since the last autosave
+    pub(crate) since_save: Duration,
+}
+
+impl AutosaveConfig {
+    /// Create a new configuration to autosave to the given path every `interval` of simulated time
+    pub fn new(path: impl Into<PathBuf>, interval: Duration) -> Self {
+        Self {
+            path: path.into(),
+            interval,
+            since_save: Duration::ZERO,
+        }
+    }
+}
@@ -1,30 +1,336 @@
 //! The `state` module contains definitions for global state
 //! contained in the engine
 
+pub mod octree;
 pub mod quadtree;
+pub mod spatial_hash;
+use generational_arena::Index;
 use indexmap::IndexMap;
-use legion::Entity;
-use quadtree::QuadTree;
-pub use quadtree::{Point, Rect};
-use serde::{Deserialize, Serialize};
+use legion::{Entity, World};
+pub use quadtree::QuadTree;
+pub use spatial_hash::SpatialHash;
+pub use octree::{Octree, Point3, AABB};
+pub use quadtree::{Point, Rect, TreeStats};
+use serde::{
+    de::{self, IgnoredAny, MapAccess, SeqAccess, Visitor},
+    ser::SerializeMap,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::fmt;
 
-use crate::gen::ProcGen;
+use crate::{
+    component::{misc::{Body, Location, Name}, physics::Radius},
+    gen::{ProcGen, ProcGenSeeded},
+    resources::GameRng,
+};
+use rand::Rng;
+
+/// A structure that stores values at points in 2D space and can find the values near a point, letting
+/// code be generic over the [QuadTree] and [SpatialHash]
+pub trait SpatialIndex<T> {
+    /// Insert a value at a point, returning `Err(val)` if the point is outside of the index's bounds
+    fn insert(&mut self, pos: Point, val: T) -> Result<(), T>;
+
+    /// Get the position and handle of every value within `radius` units of `pos`
+    fn neighbors(&self, pos: Point, radius: f32) -> Vec<(Point, Index)>;
+
+    /// Get the position and handle of every value inside of `area`
+    fn query_rect(&self, area: Rect) -> Vec<(Point, Index)>;
+
+    /// Remove the value with the given handle located at `pos`, returning the value if it was found
+    fn remove(&mut self, pos: Point, handle: Index) -> Option<T>;
+
+    /// Get a reference to the value with the given handle
+    fn get(&self, handle: Index) -> Option<&T>;
+
+    /// Get the number of values in the index
+    fn len(&self) -> usize;
+
+    /// Get the bounds that every value in the index must be located in
+    fn bounds(&self) -> Rect;
+
+    /// Iterate over every value in the index and its position
+    fn iter(&self) -> Box<dyn Iterator<Item = (Point, &T)> + '_>;
+
+    /// Check if the index contains no values
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The version of the [State] layout written by this build, which is saved with the state so that states
+/// saved by older builds can be upgraded with [migrate](State::migrate)
+///
+/// States saved before the version was added have no version, and are read as version 1
+pub const STATE_VERSION: u32 = 2;
 
 /// The `State` struct holds all elements of global game state
-#[derive(Debug, Deserialize, Serialize, Default)]
+#[derive(Debug, Default)]
 pub struct State {
     /// The container for all star systems
     galaxy: Galaxy,
 }
 
+/// The fields of a saved [State] in any version before they are upgraded to the current layout, with
+/// every field missing from the save left as `None`
+#[derive(Debug, Default)]
+pub struct RawState {
+    /// The container for all star systems
+    pub galaxy: Option<Galaxy>,
+}
+
+/// An error returned when a saved [State] can't be upgraded to the current layout
+#[derive(Debug, PartialEq, Eq)]
+pub enum MigrateError {
+    /// The state was saved by a newer build with a layout this build doesn't understand
+    TooNew(u32),
+    /// The version isn't one that any build has saved
+    Unknown(u32),
+}
+
+impl fmt::Display for MigrateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooNew(version) => write!(
+                f,
+                "State was saved with version {}, but only versions up to {} can be loaded",
+                version, STATE_VERSION
+            ),
+            Self::Unknown(version) => write!(f, "State was saved with unknown version {}", version),
+        }
+    }
+}
+
+impl std::error::Error for MigrateError {}
+
+impl State {
+    /// Create global state with the given galaxy
+    pub fn new(galaxy: Galaxy) -> Self {
+        Self { galaxy }
+    }
+
+    /// Get the galaxy containing all star systems
+    #[inline(always)]
+    pub fn galaxy(&self) -> &Galaxy {
+        &self.galaxy
+    }
+
+    /// Get the galaxy containing all star systems mutably
+    #[inline(always)]
+    pub fn galaxy_mut(&mut self) -> &mut Galaxy {
+        &mut self.galaxy
+    }
+
+    /// Upgrade the fields of a state saved with the given version to the current layout, giving every
+    /// field that didn't exist in that version its default value
+    pub fn migrate(raw: RawState, from_version: u32) -> Result<Self, MigrateError> {
+        match from_version {
+            0 => Err(MigrateError::Unknown(from_version)),
+            //Version 1 galaxies are converted to the current layout as they are read, so the fields of every
+            //version are the same once read
+            1..=STATE_VERSION => Ok(Self {
+                galaxy: raw.galaxy.unwrap_or_default(),
+            }),
+            _ => Err(MigrateError::TooNew(from_version)),
+        }
+    }
+}
+
+impl Serialize for State {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        //Always written as a map, even by formats that write structs as sequences, so that version 1 states
+        //written as sequences can be told apart. The version is written first so it is known before any
+        //other field is read
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("version", &STATE_VERSION)?;
+        map.serialize_entry("galaxy", &self.galaxy)?;
+        map.end()
+    }
+}
 
+impl<'de> Deserialize<'de> for State {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct StateVisitor;
+        impl<'de> Visitor<'de> for StateVisitor {
+            type Value = State;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a versioned map of state fields or a version 1 sequence of state fields")
+            }
+
+            /// Deserialize a version 1 state written as a sequence of its fields
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let raw = RawState {
+                    galaxy: seq.next_element::<GalaxyV1>()?.map(Galaxy::from),
+                };
+                State::migrate(raw, 1).map_err(de::Error::custom)
+            }
+
+            /// Deserialize a state written as a map, which is version 1 if it has no version
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut version = None;
+                let mut raw = RawState::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "version" => {
+                            //Fields written by newer builds may have layouts this build can't read, so stop
+                            //before reading them
+                            let saved = map.next_value()?;
+                            if saved > STATE_VERSION {
+                                return Err(de::Error::custom(MigrateError::TooNew(saved)));
+                            }
+                            version = Some(saved);
+                        }
+                        //The version is always written first, so a galaxy read before it is from version 1
+                        "galaxy" => match version {
+                            Some(_) => raw.galaxy = Some(map.next_value()?),
+                            None => raw.galaxy = Some(map.next_value::<GalaxyV1>()?.into()),
+                        },
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+                State::migrate(raw, version.unwrap_or(1)).map_err(de::Error::custom)
+            }
+        }
+        deserializer.deserialize_any(StateVisitor)
+    }
+}
+
+
+
+/// The layout of a [StarSystem] in version 1 states, before star systems stored their own position
+#[derive(Deserialize)]
+struct StarSystemV1 {
+    /// A map of entities to their locations
+    entities: QuadTree<Entity>,
+}
+
+/// The layout of a [Galaxy] in version 1 states, where the position of each star system was only stored
+/// in the spatial index of star systems
+#[derive(Deserialize)]
+struct GalaxyV1 {
+    /// A virtual map of star system indexes in the `star_map` hashmap
+    stars: QuadTree<usize>,
+    /// A map of star system names to star system data
+    star_map: IndexMap<String, StarSystemV1>,
+}
+
+impl From<GalaxyV1> for Galaxy {
+    /// Give every star system the position it is found at in the spatial index
+    fn from(old: GalaxyV1) -> Self {
+        let positions = old
+            .stars
+            .iter()
+            .map(|(pos, idx)| (*idx, pos))
+            .collect::<std::collections::HashMap<_, _>>();
+        let star_map = old
+            .star_map
+            .into_iter()
+            .enumerate()
+            .map(|(idx, (name, system))| {
+                let pos = positions.get(&idx).copied().unwrap_or(Point(0., 0.));
+                (name, StarSystem { pos, entities: system.entities })
+            })
+            .collect();
+        Self {
+            stars: old.stars,
+            star_map,
+        }
+    }
+}
 
 /// A star system contains any entities that are currently in the star system, and
 /// is contained in the [Galaxy] struct
+///
+/// Entity locations are stored in any [SpatialIndex], which is a [QuadTree] unless another is given
+/// with [with_index](StarSystem::with_index)
 #[derive(Debug, Deserialize, Serialize)]
-pub struct StarSystem {
+pub struct StarSystem<I = QuadTree<Entity>> {
+    /// The position of this star system in the galaxy
+    pos: Point,
     /// A map of entities to their locations
-    entities: QuadTree<Entity>,
+    entities: I,
+}
+
+impl StarSystem {
+    /// Create a new empty star system that can hold entities within the given bounds
+    pub fn new(bounds: Rect) -> Self {
+        Self::with_index(QuadTree::new(bounds))
+    }
+}
+
+impl<I: SpatialIndex<Entity>> StarSystem<I> {
+    /// Create a star system that stores the locations of its entities in the given index
+    pub fn with_index(entities: I) -> Self {
+        Self {
+            pos: Point(0., 0.),
+            entities,
+        }
+    }
+
+    /// Get the position of this star system in the galaxy
+    #[inline(always)]
+    pub const fn pos(&self) -> Point {
+        self.pos
+    }
+
+    /// Get the bounds that every entity in this star system must be located in
+    #[inline]
+    pub fn bounds(&self) -> Rect {
+        self.entities.bounds()
+    }
+
+    /// Place an entity in this star system at the given position, returning `Err(entity)` if
+    /// the position is outside of the star system's bounds
+    pub fn insert_entity(&mut self, entity: Entity, pos: Point) -> Result<(), Entity> {
+        self.entities.insert(pos, entity)
+    }
+
+    /// Remove an entity at the given position from this star system, returning `true` if the entity
+    /// was found there
+    pub fn remove_entity(&mut self, entity: Entity, pos: Point) -> bool {
+        let handle = self
+            .entities
+            .neighbors(pos, 0.)
+            .into_iter()
+            .find(|(_, handle)| self.entities.get(*handle) == Some(&entity));
+        match handle {
+            Some((pos, handle)) => self.entities.remove(pos, handle).is_some(),
+            None => false,
+        }
+    }
+
+    /// Move an entity from `from` to `to`, inserting it if it was not found at `from`. Returns
+    /// `Err(entity)` without moving the entity if `to` is outside of the star system's bounds
+    pub fn relocate(&mut self, entity: Entity, from: Point, to: Point) -> Result<(), Entity> {
+        if !self.entities.bounds().contains(to) {
+            return Err(entity);
+        }
+        self.remove_entity(entity, from);
+        self.entities.insert(to, entity)
+    }
+
+    /// Get all entities and their positions within `radius` units of `pos`
+    pub fn entities_near(&self, pos: Point, radius: f32) -> Vec<(Point, Entity)> {
+        self.entities
+            .neighbors(pos, radius)
+            .into_iter()
+            .filter_map(|(pos, handle)| Some((pos, *self.entities.get(handle)?)))
+            .collect()
+    }
+
+    /// Get all entities in this star system and their positions
+    pub fn entities(&self) -> impl Iterator<Item = (Point, Entity)> + '_ {
+        self.entities.iter().map(|(pos, entity)| (pos, *entity))
+    }
 }
 
 /// The `Galaxy` struct tracks where all star systems are in the game
@@ -38,17 +344,1018 @@ pub struct Galaxy {
 
 impl Default for Galaxy {
     fn default() -> Self {
+        Self::with_bounds(Rect(Point(0., 0.), Point(10000., 10000.)))
+    }
+}
+
+/// An error returned when a star system can't be added to a [Galaxy], containing the star system that
+/// wasn't added
+#[derive(Debug)]
+pub enum AddSystemError {
+    /// A star system with the same name is already in the galaxy
+    DuplicateName(StarSystem),
+    /// The star system's position is outside of the galaxy's bounds
+    OutOfBounds(StarSystem),
+}
+
+impl AddSystemError {
+    /// Get back the star system that wasn't added
+    pub fn into_system(self) -> StarSystem {
+        match self {
+            Self::DuplicateName(system) | Self::OutOfBounds(system) => system,
+        }
+    }
+}
+
+impl fmt::Display for AddSystemError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateName(_) => write!(f, "A star system with the same name is already in the galaxy"),
+            Self::OutOfBounds(system) => write!(f, "Star system at {} is outside of the galaxy", system.pos),
+        }
+    }
+}
+
+impl std::error::Error for AddSystemError {}
+
+/// A mismatch between the spatial index of a [Galaxy] and its star systems, found by
+/// [check_invariants](Galaxy::check_invariants)
+#[cfg(debug_assertions)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvariantError {
+    /// The spatial index has a different number of entries than there are star systems
+    CountMismatch {
+        /// The number of entries in the spatial index
+        indexed: usize,
+        /// The number of star systems
+        systems: usize,
+    },
+    /// An entry in the spatial index points to a star system that doesn't exist
+    DanglingIndex {
+        /// The index of the star system that doesn't exist
+        idx: usize,
+        /// The position of the entry in the spatial index
+        pos: Point,
+    },
+    /// A star system is indexed at a different position than it is located at
+    WrongPosition {
+        /// The name of the star system
+        name: String,
+        /// The position of the star system in the spatial index
+        indexed: Point,
+        /// The position of the star system
+        actual: Point,
+    },
+    /// A star system has no entry in the spatial index
+    Unindexed {
+        /// The name of the star system
+        name: String,
+    },
+}
+
+#[cfg(debug_assertions)]
+impl fmt::Display for InvariantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CountMismatch { indexed, systems } => {
+                write!(f, "The spatial index has {} entries for {} star systems", indexed, systems)
+            }
+            Self::DanglingIndex { idx, pos } => {
+                write!(f, "The spatial index entry at {} points to missing star system {}", pos, idx)
+            }
+            Self::WrongPosition { name, indexed, actual } => {
+                write!(f, "Star system {} is indexed at {} but located at {}", name, indexed, actual)
+            }
+            Self::Unindexed { name } => write!(f, "Star system {} is not in the spatial index", name),
+        }
+    }
+}
+
+#[cfg(debug_assertions)]
+impl std::error::Error for InvariantError {}
+
+/// How a procedurally generated [Galaxy] spreads its star systems through its bounds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Distribution {
+    /// Every position in the bounds is equally likely
+    Uniform,
+    /// Star systems follow logarithmic spiral arms winding out from the center of the bounds, growing
+    /// sparser farther from the center
+    Spiral {
+        /// The number of arms, spaced evenly around the center
+        arms: u32,
+    },
+    /// Star systems are grouped around randomly placed cluster centers
+    Clustered {
+        /// The number of clusters
+        clusters: u32,
+    },
+}
+
+impl Distribution {
+    /// How tightly spiral arms wind, as the growth rate of a logarithmic spiral `r = a * e^(b * angle)`
+    const SPIRAL_WINDING: f32 = 0.3;
+    /// The distance from the center, as a fraction of the galaxy's radius, that spiral arms start at
+    const SPIRAL_CORE: f32 = 0.05;
+
+    /// Get the positions of `count` star systems placed inside of `bounds`
+    pub fn positions<R: Rng>(&self, bounds: Rect, count: usize, rng: &mut R) -> Vec<Point> {
+        let (low, high) = (bounds.low(), bounds.high());
+        let uniform = |rng: &mut R| Point(rng.gen_range(low.x()..=high.x()), rng.gen_range(low.y()..=high.y()));
+        match *self {
+            Self::Uniform | Self::Clustered { clusters: 0 } => (0..count).map(|_| uniform(rng)).collect(),
+            Self::Spiral { arms } => {
+                let center = bounds.center();
+                let radius = bounds.len().min(bounds.height()) / 2.;
+                let arms = arms.max(1);
+                (0..count)
+                    .map(|_| {
+                        //Sample the distance linearly so that systems crowd towards the core, then find the
+                        //angle of that distance along the spiral of a random arm
+                        let dist = radius * rng.gen_range(Self::SPIRAL_CORE..=1f32);
+                        let arm = rng.gen_range(0..arms) as f32 / arms as f32 * std::f32::consts::TAU;
+                        let angle = arm + (dist / (radius * Self::SPIRAL_CORE)).ln() / Self::SPIRAL_WINDING
+                            + rng.gen_range(-0.25f32..=0.25);
+                        bounds.clamp(Point(center.x() + dist * angle.cos(), center.y() + dist * angle.sin()))
+                    })
+                    .collect()
+            }
+            Self::Clustered { clusters } => {
+                let centers = (0..clusters).map(|_| uniform(rng)).collect::<Vec<_>>();
+                let spread = bounds.len().min(bounds.height()) / (4. * (clusters as f32).sqrt());
+                (0..count)
+                    .map(|_| {
+                        let center = centers[rng.gen_range(0..centers.len())];
+                        let dist = spread * rng.gen::<f32>();
+                        let angle = rng.gen_range(0f32..std::f32::consts::TAU);
+                        bounds.clamp(Point(center.x() + dist * angle.cos(), center.y() + dist * angle.sin()))
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// The seed used to procedurally generate a [Galaxy]
+#[derive(Clone, Copy, Debug)]
+pub struct GalaxySeed {
+    /// The bounds that all star systems will be placed in
+    pub bounds: Rect,
+    /// The number of star systems to generate
+    pub systems: usize,
+    /// The seed of the random number generator used to place star systems
+    pub seed: u64,
+    /// How star systems are spread through the bounds
+    pub distribution: Distribution,
+}
+
+impl ProcGenSeeded for Galaxy {
+    type Seed = GalaxySeed;
+
+    fn generate_seeded(seed: Self::Seed) -> Self {
+        let mut rng = GameRng::new(seed.seed);
+        let mut galaxy = Self::with_bounds(seed.bounds);
+        let positions = seed.distribution.positions(seed.bounds, seed.systems, &mut rng);
+        for (i, pos) in positions.into_iter().enumerate() {
+            //Positions are always in bounds and names are unique, so the system can always be added
+            let _ = galaxy.add_system(format!("System {}", i), pos, StarSystem::generate());
+        }
+        galaxy
+    }
+}
+
+impl Galaxy {
+    /// Create an empty galaxy that can hold star systems inside of the given bounds
+    pub fn with_bounds(bounds: Rect) -> Self {
         Self {
-            stars: QuadTree::new(Rect(Point(0., 0.), Point(10000., 10000.))),
-            star_map: IndexMap::new()
+            stars: QuadTree::new(bounds),
+            star_map: IndexMap::new(),
+        }
+    }
+
+    /// Get the bounds that every star system in the galaxy is inside of
+    #[inline]
+    pub fn bounds(&self) -> Rect {
+        self.stars.bounds()
+    }
+
+    /// Add a star system with the given name at a position in the galaxy, returning the star system in an
+    /// error if a star system with the same name already exists or the position is outside of the galaxy's
+    /// bounds
+    pub fn add_system(&mut self, name: impl Into<String>, pos: Point, system: StarSystem) -> Result<(), AddSystemError> {
+        let name = name.into();
+        if self.star_map.contains_key(&name) {
+            return Err(AddSystemError::DuplicateName(system));
+        }
+        self.try_add(name, pos, system)
+            .map(|_| ())
+            .map_err(AddSystemError::OutOfBounds)
+    }
+
+    /// Add a star system with the given name at a position in the galaxy, replacing any star system
+    /// with the same name and returning the replaced star system
+    ///
+    /// Returns `Err(system)` if the position is outside of the galaxy's bounds
+    pub fn try_add(&mut self, name: impl Into<String>, pos: Point, mut system: StarSystem) -> Result<Option<StarSystem>, StarSystem> {
+        let name = name.into();
+        system.pos = pos;
+        //Make sure the system can be placed before replacing an old system
+        if let Some(idx) = self.star_map.get_index_of(&name) {
+            let old_pos = self.star_map[idx].pos;
+            if self.stars.insert(pos, idx).is_err() {
+                return Err(system);
+            }
+            if let Some((_, handle)) = self.find_handle(old_pos, idx) {
+                self.stars.remove(old_pos, handle);
+            }
+            return Ok(Some(std::mem::replace(&mut self.star_map[idx], system)));
+        }
+
+        let idx = self.star_map.len();
+        match self.stars.insert(pos, idx) {
+            Ok(()) => {
+                self.star_map.insert(name, system);
+                Ok(None)
+            }
+            Err(_) => Err(system),
+        }
+    }
+
+    /// Rename a star system, keeping its position and entities. The renamed star system is moved to the
+    /// end of the galaxy's iteration order, and [InSystem](crate::component::misc::InSystem) components
+    /// naming the old star system are not updated
+    ///
+    /// Returns `false` without changing anything if no star system is named `old` or a different star
+    /// system is already named `new`
+    pub fn rename_system(&mut self, old: &str, new: impl Into<String>) -> bool {
+        let new = new.into();
+        if !self.star_map.contains_key(old) || (old != new && self.star_map.contains_key(&new)) {
+            return false;
+        }
+        let system = match self.remove_system(old) {
+            Some(system) => system,
+            None => return false,
+        };
+        //The system was in bounds before it was removed, so it can always be added back
+        self.try_add(new, system.pos, system).is_ok()
+    }
+
+    /// Remove the star system with the given name from the galaxy, returning the removed system if
+    /// it existed
+    pub fn remove_system(&mut self, name: &str) -> Option<StarSystem> {
+        let (idx, _, system) = self.star_map.swap_remove_full(name)?;
+        if let Some((pos, handle)) = self.find_handle(system.pos, idx) {
+            self.stars.remove(pos, handle);
+        }
+        //The last star system was moved to the removed system's index
+        let moved = self.star_map.len();
+        if idx < moved {
+            if let Some((_, handle)) = self.find_handle(self.star_map[idx].pos, moved) {
+                if let Some(star) = self.stars.get_mut(handle) {
+                    *star = idx;
+                }
+            }
         }
+        Some(system)
+    }
+
+    /// Get the star system with the given name
+    pub fn system(&self, name: &str) -> Option<&StarSystem> {
+        self.star_map.get(name)
+    }
+
+    /// Get the star system with the given name mutably
+    pub fn system_mut(&mut self, name: &str) -> Option<&mut StarSystem> {
+        self.star_map.get_mut(name)
+    }
+
+    /// Iterate over the names and data of every star system in the galaxy
+    pub fn systems(&self) -> impl Iterator<Item = (&str, &StarSystem)> {
+        self.star_map.iter().map(|(name, system)| (name.as_str(), system))
+    }
+
+    /// Get the number of star systems in the galaxy
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.star_map.len()
+    }
+
+    /// Check if the galaxy has no star systems
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.star_map.is_empty()
+    }
+
+    /// Check that the spatial index of star systems matches the star systems in the galaxy, returning the
+    /// first mismatch found. Only available in debug builds, for tests to call after changing the galaxy
+    #[cfg(debug_assertions)]
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        if self.stars.len() != self.star_map.len() {
+            return Err(InvariantError::CountMismatch {
+                indexed: self.stars.len(),
+                systems: self.star_map.len(),
+            });
+        }
+        for (pos, idx) in self.stars.iter() {
+            match self.star_map.get_index(*idx) {
+                Some((name, system)) if system.pos != pos => {
+                    return Err(InvariantError::WrongPosition {
+                        name: name.clone(),
+                        indexed: pos,
+                        actual: system.pos,
+                    })
+                }
+                Some(_) => (),
+                None => return Err(InvariantError::DanglingIndex { idx: *idx, pos }),
+            }
+        }
+        //With as many index entries as star systems that all point to a star system, a star system can only be
+        //missing from the index if another star system is indexed twice
+        for (idx, (name, system)) in self.star_map.iter().enumerate() {
+            if self.find_handle(system.pos, idx).is_none() {
+                return Err(InvariantError::Unindexed { name: name.clone() });
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the names and data of all star systems within `radius` units of `pos`
+    pub fn systems_within(&self, pos: Point, radius: f32) -> Vec<(&str, &StarSystem)> {
+        self.stars
+            .neighbors(pos, radius)
+            .into_iter()
+            .filter_map(|(_, handle)| {
+                let (name, system) = self.star_map.get_index(*self.stars.get(handle)?)?;
+                Some((name.as_str(), system))
+            })
+            .collect()
+    }
+
+    /// Get the position and data of the star systems inside of `area`, for rendering a map of the galaxy
+    ///
+    /// If more than `max_results` star systems are in the area, the area is split into a grid of at most
+    /// `max_results` cells and only the star system closest to the center of each cell is returned, so
+    /// the result is spread out over the whole area instead of overwhelming the renderer
+    pub fn viewport(&self, area: Rect, max_results: usize) -> Vec<(Point, &StarSystem)> {
+        let visible = self
+            .stars
+            .query_rect(area)
+            .into_iter()
+            .filter_map(|(pos, handle)| Some((pos, &self.star_map[*self.stars.get(handle)?])))
+            .collect::<Vec<_>>();
+        if visible.len() <= max_results {
+            return visible;
+        }
+
+        //The largest square grid that has no more cells than the maximum number of results
+        let cells = (max_results as f32).sqrt().floor() as usize;
+        if cells == 0 {
+            return Vec::new();
+        }
+        let (cell_len, cell_height) = (area.len() / cells as f32, area.height() / cells as f32);
+        let mut samples: Vec<Option<(Point, &StarSystem)>> = vec![None; cells * cells];
+        for (pos, system) in visible {
+            let x = (((pos.x() - area.low().x()) / cell_len) as usize).min(cells - 1);
+            let y = (((pos.y() - area.low().y()) / cell_height) as usize).min(cells - 1);
+            let center = Point(
+                area.low().x() + cell_len * (x as f32 + 0.5),
+                area.low().y() + cell_height * (y as f32 + 0.5),
+            );
+            let sample = &mut samples[y * cells + x];
+            match sample {
+                Some((old, _)) if old.distance_sq(center) <= pos.distance_sq(center) => (),
+                _ => *sample = Some((pos, system)),
+            }
+        }
+        samples.into_iter().flatten().collect()
+    }
+
+    /// Get the star system closest to `pos`, or `None` if the galaxy has no star systems
+    pub fn nearest_system(&self, pos: Point) -> Option<&StarSystem> {
+        let (_, handle) = self.stars.nearest(pos, 1).pop()?;
+        let idx = *self.stars.get(handle)?;
+        self.star_map.get_index(idx).map(|(_, system)| system)
+    }
+
+    /// Plot a course of warp jumps from one star system to another, where each jump can travel at most
+    /// `warp_range` units
+    ///
+    /// Returns the names of every star system along the shortest route including the start and end systems,
+    /// or `None` if either system doesn't exist or no route exists with the given warp range
+    pub fn route(&self, from: &str, to: &str, warp_range: f32) -> Option<Vec<String>> {
+        use std::{cmp::Ordering, collections::BinaryHeap};
+
+        /// A star system waiting to be visited, ordered so that the binary heap pops the
+        /// lowest estimated route length first
+        struct Visit {
+            estimate: f32,
+            distance: f32,
+            idx: usize,
+        }
+        impl PartialEq for Visit {
+            fn eq(&self, other: &Self) -> bool {
+                self.cmp(other) == Ordering::Equal
+            }
+        }
+        impl Eq for Visit {}
+        impl PartialOrd for Visit {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for Visit {
+            fn cmp(&self, other: &Self) -> Ordering {
+                other.estimate.total_cmp(&self.estimate)
+            }
+        }
+
+        let start = self.star_map.get_index_of(from)?;
+        let end = self.star_map.get_index_of(to)?;
+        let goal = self.star_map[end].pos;
+
+        //A* search using the straight line distance to the end system as the heuristic
+        let mut distances = vec![f32::INFINITY; self.star_map.len()];
+        let mut previous = vec![None; self.star_map.len()];
+        let mut queue = BinaryHeap::new();
+        distances[start] = 0.;
+        queue.push(Visit {
+            estimate: self.star_map[start].pos.distance(goal),
+            distance: 0.,
+            idx: start,
+        });
+
+        while let Some(Visit { distance, idx, .. }) = queue.pop() {
+            //A shorter route to this system was found after this visit was queued
+            if distance > distances[idx] {
+                continue;
+            }
+            if idx == end {
+                let mut route = vec![self.star_map.get_index(end)?.0.clone()];
+                let mut current = end;
+                while let Some(prev) = previous[current] {
+                    route.push(self.star_map.get_index(prev)?.0.clone());
+                    current = prev;
+                }
+                route.reverse();
+                return Some(route);
+            }
+
+            let pos = self.star_map[idx].pos;
+            for (_, handle) in self.stars.neighbors(pos, warp_range) {
+                let next = *self.stars.get(handle)?;
+                let distance = distances[idx] + pos.distance(self.star_map[next].pos);
+                if distance < distances[next] {
+                    distances[next] = distance;
+                    previous[next] = Some(idx);
+                    queue.push(Visit {
+                        estimate: distance + self.star_map[next].pos.distance(goal),
+                        distance,
+                        idx: next,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Find the quad tree handle of the star system index at the given position
+    fn find_handle(&self, pos: Point, idx: usize) -> Option<(Point, Index)> {
+        self.stars
+            .neighbors(pos, 0.)
+            .into_iter()
+            .find(|(_, handle)| self.stars.get(*handle) == Some(&idx))
     }
 }
 
 impl ProcGen for StarSystem {
     fn generate() -> Self {
         Self {
+            pos: Point(0., 0.),
             entities: QuadTree::new(Rect(Point(0., 0.), Point(0., 0.))),
         }
     }
 }
+
+/// A kind of star system that [generate_template](StarSystem::generate_template) can create, each placing
+/// a characteristic set of bodies
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SystemTemplate {
+    /// One star orbited by planets
+    Solar,
+    /// Two stars orbiting close to the center, with planets orbiting both
+    BinaryStar,
+    /// One star surrounded by a belt of asteroids and a few planets
+    AsteroidField,
+    /// Empty space with no bodies at all
+    DeepSpace,
+}
+
+impl SystemTemplate {
+    /// Every star system template
+    pub const ALL: [Self; 4] = [Self::Solar, Self::BinaryStar, Self::AsteroidField, Self::DeepSpace];
+
+    /// Choose a template at random, with solar systems being the most common and deep space the least
+    pub fn choose(rng: &mut impl Rng) -> Self {
+        match rng.gen_range(0..10) {
+            0..=4 => Self::Solar,
+            5 | 6 => Self::BinaryStar,
+            7 | 8 => Self::AsteroidField,
+            _ => Self::DeepSpace,
+        }
+    }
+
+    /// Get the number of stars, planets, and asteroids that this template places
+    pub const fn bodies(&self) -> (usize, usize, usize) {
+        match self {
+            Self::Solar => (1, 5, 0),
+            Self::BinaryStar => (2, 3, 0),
+            Self::AsteroidField => (1, 2, 40),
+            Self::DeepSpace => (0, 0, 0),
+        }
+    }
+}
+
+impl StarSystem {
+    /// Generate a star system with the given bounds holding the bodies of a template, adding an entity with a
+    /// [Name], [Location], [Radius], and [Body] to the world for every body. Every body is placed inside of
+    /// the bounds, at a distance from the center scaled to the smaller side of the bounds
+    pub fn generate_template(template: SystemTemplate, bounds: Rect, world: &mut World, rng: &mut impl Rng) -> Self {
+        use std::f32::consts::TAU;
+
+        let mut system = Self::new(bounds);
+        let center = bounds.center();
+        let size = bounds.len().min(bounds.height()) / 2.;
+        let mut place = |body: Body, name: String, dist: f32, angle: f32, r: f32| {
+            let loc = Point(center.x() + dist * angle.cos(), center.y() + dist * angle.sin());
+            let entity = world.push((Name { name }, Location { loc }, Radius { r }, body));
+            //Bodies are never further from the center than the edge of the bounds, so they can always be placed
+            let _ = system.insert_entity(entity, loc);
+        };
+
+        let (stars, planets, asteroids) = template.bodies();
+        let angle = rng.gen_range(0.0..TAU);
+        for i in 0..stars {
+            //Multiple stars are spread evenly around the center
+            let dist = if stars > 1 { size * 0.1 } else { 0. };
+            let angle = angle + i as f32 * TAU / stars as f32;
+            place(Body::Star, format!("Star {}", i + 1), dist, angle, size * 0.03);
+        }
+        for i in 0..planets {
+            let (dist, angle) = (rng.gen_range(0.25f32..0.9) * size, rng.gen_range(0.0..TAU));
+            place(Body::Planet, format!("Planet {}", i + 1), dist, angle, size * 0.01);
+        }
+        for i in 0..asteroids {
+            let (dist, angle) = (rng.gen_range(0.5f32..0.7) * size, rng.gen_range(0.0..TAU));
+            place(Body::Asteroid, format!("Asteroid {}", i + 1), dist, angle, size * 0.002);
+        }
+        system
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use generational_arena::Arena;
+
+    #[test]
+    pub fn test_route() {
+        let mut galaxy = Galaxy::default();
+        galaxy.add_system("Sol", Point(100., 100.), StarSystem::generate()).unwrap();
+        galaxy.add_system("Alpha Centauri", Point(140., 100.), StarSystem::generate()).unwrap();
+        galaxy.add_system("Sirius", Point(180., 110.), StarSystem::generate()).unwrap();
+        galaxy.add_system("Vega", Point(900., 900.), StarSystem::generate()).unwrap();
+
+        assert_eq!(
+            galaxy.route("Sol", "Sirius", 50.),
+            Some(vec!["Sol".to_owned(), "Alpha Centauri".to_owned(), "Sirius".to_owned()])
+        );
+        assert_eq!(
+            galaxy.route("Sol", "Sirius", 100.),
+            Some(vec!["Sol".to_owned(), "Sirius".to_owned()])
+        );
+        assert_eq!(galaxy.route("Sol", "Sol", 1.), Some(vec!["Sol".to_owned()]));
+        assert_eq!(galaxy.route("Sol", "Vega", 50.), None);
+        assert_eq!(galaxy.route("Sol", "Betelgeuse", 50.), None);
+    }
+
+    /// The layout of a [QuadTree] in version 1 states, before its maximum depth was saved with it
+    #[derive(Serialize)]
+    struct QuadTreeV1<T> {
+        arena: Arena<T>,
+        root: BranchV1,
+    }
+
+    /// The layout of a quad tree branch in version 1 states
+    #[derive(Serialize)]
+    struct BranchV1 {
+        bb: Rect,
+        children: Box<[Option<NodeV1>; 4]>,
+    }
+
+    /// The layout of a quad tree node in version 1 states, before buckets were added
+    #[derive(Serialize)]
+    enum NodeV1 {
+        Branch(BranchV1),
+        Leaf((Point, Index)),
+    }
+
+    /// The layout of a [StarSystem] in version 1 states
+    #[derive(Serialize)]
+    struct SavedSystemV1 {
+        entities: QuadTreeV1<Entity>,
+    }
+
+    /// The layout of a [Galaxy] in version 1 states
+    #[derive(Serialize)]
+    struct SavedGalaxyV1 {
+        stars: QuadTreeV1<usize>,
+        star_map: IndexMap<String, SavedSystemV1>,
+    }
+
+    /// The layout of [State] before its version was saved with it
+    #[derive(Serialize)]
+    struct StateV1 {
+        galaxy: SavedGalaxyV1,
+    }
+
+    /// Build a version 1 state with Sol at (100, 100) and Sirius at (400, 300), laid out in the quad tree the
+    /// way a version 1 build inserted them
+    fn state_v1(bounds: Rect) -> StateV1 {
+        let empty = || SavedSystemV1 {
+            entities: QuadTreeV1 {
+                arena: Arena::new(),
+                root: BranchV1 {
+                    bb: Rect(Point(0., 0.), Point(0., 0.)),
+                    children: Box::new([None, None, None, None]),
+                },
+            },
+        };
+        let mut arena = Arena::new();
+        let sol = arena.insert(0);
+        let sirius = arena.insert(1);
+        let sol_branch = BranchV1 {
+            bb: bounds.sw(),
+            children: Box::new([None, None, None, Some(NodeV1::Leaf((Point(100., 100.), sol)))]),
+        };
+        let stars = QuadTreeV1 {
+            arena,
+            root: BranchV1 {
+                bb: bounds,
+                children: Box::new([
+                    None,
+                    Some(NodeV1::Leaf((Point(400., 300.), sirius))),
+                    None,
+                    Some(NodeV1::Branch(sol_branch)),
+                ]),
+            },
+        };
+        let mut star_map = IndexMap::new();
+        star_map.insert("Sol".to_owned(), empty());
+        star_map.insert("Sirius".to_owned(), empty());
+        StateV1 {
+            galaxy: SavedGalaxyV1 { stars, star_map },
+        }
+    }
+
+    /// A [State] saved by a newer build that changed the layout of the galaxy
+    #[derive(Serialize)]
+    struct FutureState {
+        version: u32,
+        galaxy: String,
+    }
+
+    #[test]
+    pub fn test_migrate() {
+        let bounds = Rect::new(Point(0., 0.), Point(500., 500.));
+        //Version 1 states were written as sequences, or as maps without a version
+        for v1 in [rmp_serde::to_vec(&state_v1(bounds)), rmp_serde::to_vec_named(&state_v1(bounds))] {
+            let state: State = rmp_serde::from_read_ref(&v1.unwrap()).unwrap();
+            assert_eq!(state.galaxy().bounds(), bounds);
+            assert_eq!(state.galaxy().len(), 2);
+            assert_eq!(state.galaxy().system("Sol").unwrap().pos(), Point(100., 100.));
+            assert_eq!(state.galaxy().system("Sirius").unwrap().pos(), Point(400., 300.));
+            let found = state.galaxy().systems_within(Point(100., 100.), 1.);
+            assert_eq!(found.len(), 1);
+            assert_eq!(found[0].0, "Sol");
+        }
+
+        //Fields missing from older states are given their default values
+        let empty = rmp_serde::to_vec(&std::collections::HashMap::<String, u32>::new()).unwrap();
+        let state: State = rmp_serde::from_read_ref(&empty).unwrap();
+        assert_eq!(state.galaxy().bounds(), Galaxy::default().bounds());
+        assert_eq!(state.galaxy().len(), 0);
+
+        let saved = rmp_serde::to_vec(&State::new(Galaxy::with_bounds(bounds))).unwrap();
+        let loaded: State = rmp_serde::from_read_ref(&saved).unwrap();
+        assert_eq!(loaded.galaxy().bounds(), bounds);
+
+        let newer = rmp_serde::to_vec(&[("version", STATE_VERSION + 1)].iter().copied().collect::<std::collections::HashMap<_, _>>()).unwrap();
+        let err = rmp_serde::from_read_ref::<_, State>(&newer).unwrap_err();
+        assert!(err.to_string().contains(&MigrateError::TooNew(STATE_VERSION + 1).to_string()), "{}", err);
+        //The version is checked before a galaxy with a layout this build can't read
+        let newer = rmp_serde::to_vec_named(&FutureState {
+            version: STATE_VERSION + 1,
+            galaxy: "A galaxy layout from a newer build".to_owned(),
+        })
+        .unwrap();
+        let err = rmp_serde::from_read_ref::<_, State>(&newer).unwrap_err();
+        assert!(err.to_string().contains(&MigrateError::TooNew(STATE_VERSION + 1).to_string()), "{}", err);
+        assert_eq!(State::migrate(RawState::default(), 0).unwrap_err(), MigrateError::Unknown(0));
+    }
+
+    #[test]
+    pub fn test_viewport() {
+        let mut galaxy = Galaxy::default();
+        for x in 0..20 {
+            for y in 0..20 {
+                let pos = Point(100. + x as f32 * 5., 100. + y as f32 * 5.);
+                galaxy.add_system(format!("{} {}", x, y), pos, StarSystem::generate()).unwrap();
+            }
+        }
+        galaxy.add_system("Distant", Point(5000., 5000.), StarSystem::generate()).unwrap();
+
+        let area = Rect::new(Point(90., 90.), Point(210., 210.));
+        assert_eq!(galaxy.viewport(area, 1000).len(), 400);
+
+        let sampled = galaxy.viewport(area, 16);
+        assert!(!sampled.is_empty() && sampled.len() <= 16, "{} results", sampled.len());
+        assert!(sampled.iter().all(|(pos, _)| area.contains(*pos)));
+        //Every quadrant of the viewport is represented
+        for quadrant in [area.nw(), area.ne(), area.se(), area.sw()] {
+            assert!(sampled.iter().any(|(pos, _)| quadrant.contains(*pos)), "Nothing sampled in {}", quadrant);
+        }
+        assert!(galaxy.viewport(area, 0).is_empty());
+    }
+
+    #[test]
+    pub fn test_nearest_system() {
+        let mut galaxy = Galaxy::default();
+        assert!(galaxy.nearest_system(Point(0., 0.)).is_none());
+        for (name, pos) in [("Sol", Point(100., 100.)), ("Sirius", Point(180., 110.)), ("Vega", Point(900., 40.)), ("Rigel", Point(400., 700.))] {
+            galaxy.add_system(name, pos, StarSystem::generate()).unwrap();
+        }
+        assert_eq!(galaxy.nearest_system(Point(150., 100.)).unwrap().pos(), Point(180., 110.));
+        assert_eq!(galaxy.nearest_system(Point(0., 0.)).unwrap().pos(), Point(100., 100.));
+        assert_eq!(galaxy.nearest_system(Point(10000., 0.)).unwrap().pos(), Point(900., 40.));
+
+        //Removing a system moves another system's index, which must still resolve correctly
+        galaxy.remove_system("Sol").unwrap();
+        assert_eq!(galaxy.nearest_system(Point(390., 650.)).unwrap().pos(), Point(400., 700.));
+        assert_eq!(galaxy.nearest_system(Point(0., 0.)).unwrap().pos(), Point(180., 110.));
+    }
+
+    #[test]
+    pub fn test_systems_within() {
+        let mut galaxy = Galaxy::default();
+        galaxy.add_system("Sol", Point(100., 100.), StarSystem::generate()).unwrap();
+        galaxy.add_system("Sirius", Point(180., 110.), StarSystem::generate()).unwrap();
+        assert!(galaxy.add_system("Outside", Point(-5., 10.), StarSystem::generate()).is_err());
+
+        let mut names = galaxy
+            .systems_within(Point(120., 100.), 70.)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        assert_eq!(names, vec!["Sirius", "Sol"]);
+
+        //Replacing a system moves it in the spatial index
+        assert!(galaxy.try_add("Sol", Point(500., 500.), StarSystem::generate()).unwrap().is_some());
+        assert_eq!(galaxy.len(), 2);
+        assert_eq!(galaxy.system("Sol").unwrap().pos(), Point(500., 500.));
+        assert_eq!(galaxy.systems_within(Point(100., 100.), 10.).len(), 0);
+        assert_eq!(galaxy.systems_within(Point(500., 500.), 10.).len(), 1);
+    }
+
+    #[test]
+    pub fn test_negative_systems_within() {
+        let mut galaxy = Galaxy::with_bounds(Rect::new(Point(-1000., -1000.), Point(1000., 1000.)));
+        galaxy.add_system("Sol", Point(-100., -100.), StarSystem::generate()).unwrap();
+        galaxy.add_system("Sirius", Point(-130., -90.), StarSystem::generate()).unwrap();
+        galaxy.add_system("Vega", Point(100., 100.), StarSystem::generate()).unwrap();
+        let mut names = galaxy
+            .systems_within(Point(-110., -100.), 30.)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        assert_eq!(names, vec!["Sirius", "Sol"]);
+
+        let mut galaxy = Galaxy::with_bounds(Rect::new(Point(-100., -100.), Point(-10., -10.)));
+        galaxy.add_system("Sol", Point(-50., -50.), StarSystem::generate()).unwrap();
+        assert_eq!(galaxy.systems_within(Point(-52., -50.), 5.).len(), 1);
+        assert!(galaxy.systems_within(Point(-20., -20.), 5.).is_empty());
+    }
+
+    #[test]
+    pub fn test_duplicate_name() {
+        let mut galaxy = Galaxy::default();
+        galaxy.add_system("Sol", Point(100., 100.), StarSystem::generate()).unwrap();
+        assert!(matches!(
+            galaxy.add_system("Sol", Point(500., 500.), StarSystem::generate()),
+            Err(AddSystemError::DuplicateName(_))
+        ));
+        assert!(matches!(
+            galaxy.add_system("Outside", Point(-5., 10.), StarSystem::generate()),
+            Err(AddSystemError::OutOfBounds(_))
+        ));
+        assert_eq!(galaxy.len(), 1);
+        assert_eq!(galaxy.system("Sol").unwrap().pos(), Point(100., 100.));
+        assert_eq!(galaxy.systems_within(Point(500., 500.), 1.).len(), 0);
+    }
+
+    #[test]
+    pub fn test_rename_system() {
+        let mut world = legion::World::default();
+        let station = world.push(());
+        let mut sol = StarSystem::new(Rect::new(Point(0., 0.), Point(100., 100.)));
+        sol.insert_entity(station, Point(20., 30.)).unwrap();
+
+        let mut galaxy = Galaxy::default();
+        galaxy.add_system("Sol", Point(100., 100.), sol).unwrap();
+        galaxy.add_system("Sirius", Point(180., 110.), StarSystem::generate()).unwrap();
+        galaxy.add_system("Vega", Point(900., 900.), StarSystem::generate()).unwrap();
+
+        assert!(!galaxy.rename_system("Sol", "Vega"));
+        assert!(!galaxy.rename_system("Polaris", "Rigel"));
+        assert!(galaxy.rename_system("Sol", "Home"));
+        assert!(galaxy.system("Sol").is_none());
+        assert_eq!(galaxy.len(), 3);
+
+        let home = galaxy.system("Home").unwrap();
+        assert_eq!(home.pos(), Point(100., 100.));
+        assert_eq!(home.entities().collect::<Vec<_>>(), vec![(Point(20., 30.), station)]);
+        //Every star system must still be found at its position in the spatial index
+        for (name, pos) in [("Home", Point(100., 100.)), ("Sirius", Point(180., 110.)), ("Vega", Point(900., 900.))] {
+            let found = galaxy.systems_within(pos, 1.);
+            assert_eq!(found.len(), 1);
+            assert_eq!(found[0].0, name);
+        }
+    }
+
+    #[test]
+    pub fn test_generate_template() {
+        let bounds = Rect::new(Point(-500., 0.), Point(500., 2000.));
+        let mut rng = GameRng::new(3);
+        let expected = [
+            (SystemTemplate::Solar, [1, 5, 0]),
+            (SystemTemplate::BinaryStar, [2, 3, 0]),
+            (SystemTemplate::AsteroidField, [1, 2, 40]),
+            (SystemTemplate::DeepSpace, [0, 0, 0]),
+        ];
+        for (template, counts) in expected {
+            let mut world = World::default();
+            let system = StarSystem::generate_template(template, bounds, &mut world, &mut rng);
+            assert_eq!(system.bounds(), bounds);
+
+            let mut found = [0; 3];
+            for (pos, entity) in system.entities() {
+                assert!(bounds.contains(pos), "{:?} placed a body outside of the bounds at {}", template, pos);
+                let entry = world.entry_ref(entity).unwrap();
+                assert_eq!(entry.get_component::<Location>().unwrap().loc, pos);
+                found[*entry.get_component::<Body>().unwrap() as usize] += 1;
+            }
+            assert_eq!(found, counts, "{:?}", template);
+            assert_eq!(world.len(), counts.iter().sum::<usize>());
+        }
+    }
+
+    #[test]
+    pub fn test_generate_bounds() {
+        let bounds = Rect::new(Point(-50000., 20000.), Point(50000., 90000.));
+        let galaxy = Galaxy::generate_seeded(GalaxySeed {
+            bounds,
+            systems: 200,
+            seed: 7,
+            distribution: Distribution::Uniform,
+        });
+        assert_eq!(galaxy.bounds(), bounds);
+        assert_eq!(galaxy.len(), 200);
+        for (_, system) in galaxy.systems() {
+            assert!(bounds.contains(system.pos()));
+        }
+        //Some systems fall outside of the default galaxy's bounds
+        assert!(galaxy.systems().any(|(_, system)| !Galaxy::default().bounds().contains(system.pos())));
+    }
+
+    #[test]
+    pub fn test_generate_spiral() {
+        let bounds = Rect::new(Point(-1000., -1000.), Point(1000., 1000.));
+        let seed = GalaxySeed {
+            bounds,
+            systems: 500,
+            seed: 3,
+            distribution: Distribution::Spiral { arms: 2 },
+        };
+        let positions = |galaxy: &Galaxy| galaxy.systems().map(|(_, system)| system.pos()).collect::<Vec<_>>();
+        let spiral = positions(&Galaxy::generate_seeded(seed));
+        assert_eq!(spiral, positions(&Galaxy::generate_seeded(seed)));
+        assert_eq!(spiral.len(), 500);
+        assert!(spiral.iter().all(|pos| bounds.contains(*pos)));
+
+        //A uniform galaxy has about a fifth of its systems within half of the radius of the center, but a
+        //spiral galaxy crowds towards its core
+        let core = |positions: &[Point]| {
+            positions.iter().filter(|pos| pos.distance(Point(0., 0.)) < 500.).count() as f32 / positions.len() as f32
+        };
+        let uniform = positions(&Galaxy::generate_seeded(GalaxySeed {
+            distribution: Distribution::Uniform,
+            ..seed
+        }));
+        assert!(core(&uniform) < 0.3, "{}", core(&uniform));
+        assert!(core(&spiral) > 0.35, "{}", core(&spiral));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    pub fn test_check_invariants() {
+        let mut galaxy = Galaxy::default();
+        assert_eq!(galaxy.check_invariants(), Ok(()));
+        for i in 0..10 {
+            galaxy.add_system(format!("System {}", i), Point(i as f32 * 50. + 10., 100.), StarSystem::generate()).unwrap();
+            galaxy.check_invariants().unwrap();
+        }
+        //Removing star systems moves the last star system into the removed index
+        for name in ["System 0", "System 5", "System 9", "System 3"] {
+            galaxy.remove_system(name).unwrap();
+            galaxy.check_invariants().unwrap();
+        }
+        galaxy.try_add("System 4", Point(900., 900.), StarSystem::generate()).unwrap();
+        galaxy.check_invariants().unwrap();
+        assert!(galaxy.rename_system("System 1", "Sol"));
+        galaxy.check_invariants().unwrap();
+        galaxy.add_system("System 0", Point(10., 100.), StarSystem::generate()).unwrap();
+        galaxy.check_invariants().unwrap();
+        assert_eq!(galaxy.len(), 7);
+
+        //Corrupt the spatial index by moving a star system without updating it
+        galaxy.system_mut("Sol").unwrap().pos = Point(1., 1.);
+        assert_eq!(
+            galaxy.check_invariants(),
+            Err(InvariantError::WrongPosition {
+                name: "Sol".to_owned(),
+                indexed: Point(60., 100.),
+                actual: Point(1., 1.),
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_remove_system() {
+        let mut galaxy = Galaxy::default();
+        galaxy.add_system("Sol", Point(100., 100.), StarSystem::generate()).unwrap();
+        galaxy.add_system("Sirius", Point(180., 110.), StarSystem::generate()).unwrap();
+        galaxy.add_system("Vega", Point(900., 900.), StarSystem::generate()).unwrap();
+
+        assert_eq!(galaxy.remove_system("Sol").unwrap().pos(), Point(100., 100.));
+        assert!(galaxy.remove_system("Sol").is_none());
+        assert_eq!(galaxy.len(), 2);
+        assert!(galaxy.systems_within(Point(100., 100.), 1.).is_empty());
+        //The last system was moved in the map, so its spatial index entry must still resolve to it
+        let found = galaxy.systems_within(Point(900., 900.), 1.);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, "Vega");
+    }
+
+    #[test]
+    pub fn test_spatial_index_object() {
+        let mut quad = QuadTree::new(Rect::new(Point(0., 0.), Point(100., 100.)));
+        let index: &mut dyn SpatialIndex<u32> = &mut quad;
+        assert!(index.is_empty());
+        index.insert(Point(10., 10.), 1).unwrap();
+        index.insert(Point(12., 12.), 2).unwrap();
+        index.insert(Point(80., 20.), 3).unwrap();
+        assert_eq!(index.insert(Point(150., 10.), 4), Err(4));
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.bounds(), Rect::new(Point(0., 0.), Point(100., 100.)));
+
+        let mut near = index
+            .neighbors(Point(11., 11.), 2.)
+            .into_iter()
+            .filter_map(|(_, handle)| index.get(handle).copied())
+            .collect::<Vec<_>>();
+        near.sort_unstable();
+        assert_eq!(near, vec![1, 2]);
+
+        let found = index.query_rect(Rect::new(Point(50., 0.), Point(100., 50.)));
+        assert_eq!(found.len(), 1);
+        let (pos, handle) = found[0];
+        assert_eq!(pos, Point(80., 20.));
+        assert_eq!(index.remove(pos, handle), Some(3));
+        assert_eq!(index.remove(pos, handle), None);
+        assert!(index.query_rect(Rect::new(Point(50., 0.), Point(100., 50.))).is_empty());
+        assert_eq!(index.len(), 2);
+        let mut values = index.iter().map(|(_, val)| *val).collect::<Vec<_>>();
+        values.sort_unstable();
+        assert_eq!(values, vec![1, 2]);
+
+        //A star system behaves the same with any index
+        let mut world = legion::World::default();
+        let station = world.push(());
+        let mut system = StarSystem::with_index(SpatialHash::new(Rect::new(Point(0., 0.), Point(100., 100.)), 10.));
+        system.insert_entity(station, Point(20., 30.)).unwrap();
+        assert_eq!(system.entities_near(Point(21., 30.), 2.), vec![(Point(20., 30.), station)]);
+        system.relocate(station, Point(20., 30.), Point(70., 70.)).unwrap();
+        assert_eq!(system.entities().collect::<Vec<_>>(), vec![(Point(70., 70.), station)]);
+    }
+}
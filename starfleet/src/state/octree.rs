@@ -0,0 +1,445 @@
+//! An octree structure for efficiently storing positions in 3D space, the 3D counterpart of
+//! the [QuadTree](super::QuadTree)
+use generational_arena::{Arena, Index};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use super::quadtree::DEFAULT_MAX_DEPTH;
+
+/// The `OctBranch` struct is used in the [Branch](OctNode::Branch) variant of the [OctNode] enum,
+/// and contains a bounding box for the contained nodes and the child nodes
+///
+/// Child nodes are indexed by octant, with bit 0 set for the high X half, bit 1 set for the high
+/// Y half, and bit 2 set for the high Z half of the bounding box
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OctBranch {
+    /// The bounding box of this branch
+    bb: AABB,
+    /// A branch always has at most 8 children
+    children: Box<[Option<OctNode>; 8]>,
+}
+
+impl OctBranch {
+    /// Create a new branch with no children using the given bounding box
+    fn new(bb: AABB) -> Self {
+        Self {
+            bb,
+            children: Box::new([None, None, None, None, None, None, None, None]),
+        }
+    }
+
+    /// Insert the given point into the branch at the given depth, returning `true` if the value was inserted
+    fn insert(&mut self, pos: Point3, val: Index, depth: usize, max_depth: usize) -> bool {
+        if !self.bb.contains(pos) {
+            return false;
+        }
+
+        //Find the first octant that this point is in
+        for (octant, child) in self.children.iter_mut().enumerate() {
+            let area = self.bb.octant(octant);
+            if area.contains(pos) {
+                return match child {
+                    Some(node) => node.insert(pos, val, area, depth + 1, max_depth),
+                    node @ None => {
+                        *node = Some(OctNode::Leaf((pos, val)));
+                        true
+                    }
+                };
+            }
+        }
+        unreachable!("One of the child nodes must contain the point")
+    }
+
+    /// Remove the leaf with the given handle at the given point, returning `true` if the leaf was found
+    fn remove(&mut self, pos: Point3, val: Index) -> bool {
+        for (octant, child) in self.children.iter_mut().enumerate() {
+            //Points on the edge of an octant can be contained in more than one child
+            if !self.bb.octant(octant).contains(pos) {
+                continue;
+            }
+            let removed = match child {
+                Some(OctNode::Branch(branch)) => branch.remove(pos, val),
+                Some(OctNode::Leaf((_, idx))) if *idx == val => {
+                    *child = None;
+                    true
+                }
+                Some(OctNode::Bucket(points)) => match points.iter().position(|(_, idx)| *idx == val) {
+                    Some(i) => {
+                        points.swap_remove(i);
+                        if points.is_empty() {
+                            *child = None;
+                        }
+                        true
+                    }
+                    None => false,
+                },
+                _ => false,
+            };
+            if removed {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Get the neighbors within a certain radius of a point
+    fn neighbors(&self, pos: Point3, radius: f32, neighbors: &mut Vec<(Point3, Index)>) {
+        let offset = Point3(radius, radius, radius);
+        let search_bb = AABB(pos - offset, pos + offset);
+        //Make sure this branch actually can contain a point in the search area
+        if self.bb.intersects(search_bb) {
+            for child in self.children.iter().flatten() {
+                child.neighbors(pos, radius, neighbors)
+            }
+        }
+    }
+}
+
+/// One node in an [Octree], either containing more children or a leaf node
+#[derive(Debug, Serialize, Deserialize)]
+pub enum OctNode {
+    /// A branch in the tree, containing children nodes
+    Branch(OctBranch),
+    /// A leaf node with position and data
+    Leaf((Point3, Index)),
+    /// A leaf node at the maximum depth of the tree, holding every point that reaches it instead of
+    /// splitting into a branch
+    Bucket(Vec<(Point3, Index)>),
+}
+
+impl OctNode {
+    /// Insert a handle into this node at the given depth, either inserting into a child node or
+    /// splitting this leaf into a branch. Leaves at `max_depth` become buckets instead of splitting
+    ///
+    /// Returns `true` if the value was inserted and `false` if insertion failed
+    fn insert(&mut self, pos: Point3, val: Index, area: AABB, depth: usize, max_depth: usize) -> bool {
+        match self {
+            Self::Branch(branch) => branch.insert(pos, val, depth, max_depth),
+            Self::Leaf(_) | Self::Bucket(_) if !area.contains(pos) => false,
+            Self::Leaf(old) if depth >= max_depth => {
+                *self = Self::Bucket(vec![*old, (pos, val)]);
+                true
+            }
+            //We need to split into octants
+            Self::Leaf((old_point, old_handle)) => {
+                let mut split = OctBranch::new(area);
+                split.insert(*old_point, *old_handle, depth, max_depth);
+                if split.insert(pos, val, depth, max_depth) {
+                    *self = Self::Branch(split);
+                    true
+                } else {
+                    false
+                }
+            }
+            Self::Bucket(points) => {
+                points.push((pos, val));
+                true
+            }
+        }
+    }
+
+    /// Get all neighbors `radius` units from `pos`
+    fn neighbors(&self, pos: Point3, radius: f32, neighbors: &mut Vec<(Point3, Index)>) {
+        match self {
+            Self::Branch(branch) => branch.neighbors(pos, radius, neighbors),
+            Self::Leaf((leaf_pos, idx)) => {
+                if leaf_pos.distance(pos) <= radius {
+                    neighbors.push((*leaf_pos, *idx))
+                }
+            }
+            Self::Bucket(points) => {
+                neighbors.extend(points.iter().filter(|(leaf_pos, _)| leaf_pos.distance(pos) <= radius))
+            }
+        }
+    }
+}
+
+/// The `Octree` struct is used to hold a record of locations in a 3D coordinate space
+#[derive(Serialize, Deserialize)]
+pub struct Octree<T> {
+    /// Arena allocator we store all nodes in
+    arena: Arena<T>,
+    /// The root node of the octree
+    root: OctBranch,
+    /// The maximum depth of any leaf, past which leaves hold many points instead of splitting
+    #[serde(default = "default_max_depth")]
+    max_depth: usize,
+}
+
+/// Get the [DEFAULT_MAX_DEPTH] for octrees saved before their maximum depth was saved with them
+fn default_max_depth() -> usize {
+    DEFAULT_MAX_DEPTH
+}
+
+impl<T> Octree<T> {
+    /// Return a new [Octree] with the maximum given bounds and a maximum depth of [DEFAULT_MAX_DEPTH]
+    pub fn new(bounds: AABB) -> Self {
+        Self::with_max_depth(bounds, DEFAULT_MAX_DEPTH)
+    }
+
+    /// Return a new [Octree] with the maximum given bounds, whose leaves stop splitting once they are
+    /// `max_depth` levels deep so that coincident points share a leaf instead of splitting forever
+    pub fn with_max_depth(bounds: AABB, max_depth: usize) -> Self {
+        Self {
+            arena: Arena::new(),
+            root: OctBranch::new(bounds),
+            max_depth,
+        }
+    }
+
+    /// Insert a given value into the octree and return `Ok(())` if the point is able to be contained
+    /// in this octree and was inserted, or `Err(val)` if it is not
+    pub fn insert(&mut self, pos: Point3, val: T) -> Result<(), T> {
+        let handle = self.arena.insert(val);
+        match self.root.insert(pos, handle, 0, self.max_depth) {
+            true => Ok(()),
+            false => Err(self.arena.remove(handle).unwrap()),
+        }
+    }
+
+    /// Get a list of all neighbors by searching in a sphere around a point
+    pub fn neighbors(&self, pos: Point3, radius: f32) -> Vec<(Point3, Index)> {
+        let mut neighbors = Vec::new();
+        self.root.neighbors(pos, radius, &mut neighbors);
+        neighbors
+    }
+
+    /// Remove the value with the given handle located at `pos`, returning the value if it was
+    /// contained in this octree
+    pub fn remove(&mut self, pos: Point3, handle: Index) -> Option<T> {
+        match self.root.remove(pos, handle) {
+            true => self.arena.remove(handle),
+            false => None,
+        }
+    }
+
+    /// Get a reference to the value with the given handle
+    #[inline]
+    pub fn get(&self, handle: Index) -> Option<&T> {
+        self.arena.get(handle)
+    }
+
+    /// Get a mutable reference to the value with the given handle
+    #[inline]
+    pub fn get_mut(&mut self, handle: Index) -> Option<&mut T> {
+        self.arena.get_mut(handle)
+    }
+
+    /// Get the number of values contained in this octree
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.arena.len()
+    }
+
+    /// Check if this octree contains no values
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.arena.is_empty()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Octree<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Octree")
+            .field("bounds", &self.root.bb)
+            .field("values", &self.arena.iter().map(|(_, val)| val).collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// The `Point3` struct stores a position in 3D space
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Point3(pub f32, pub f32, pub f32);
+
+impl Point3 {
+    #[inline(always)]
+    pub const fn x(&self) -> f32 {
+        self.0
+    }
+
+    #[inline(always)]
+    pub const fn y(&self) -> f32 {
+        self.1
+    }
+
+    #[inline(always)]
+    pub const fn z(&self) -> f32 {
+        self.2
+    }
+
+    /// Return the distance between this point and another point
+    pub fn distance(&self, other: Self) -> f32 {
+        ((other.0 - self.0).powi(2) + (other.1 - self.1).powi(2) + (other.2 - self.2).powi(2)).sqrt()
+    }
+}
+
+macro_rules! impl_op {
+    ($op:tt , $name:ident , $fn:ident) => {
+        impl ::std::ops::$name for Point3 {
+            type Output = Self;
+            fn $fn (self, rhs: Self) -> Self::Output {
+                Self(self.0 $op rhs.0, self.1 $op rhs.1, self.2 $op rhs.2)
+            }
+        }
+    };
+    ($op:tt, $name:ident, $fn:ident -assign) => {
+        impl ::std::ops::$name for Point3 {
+            fn $fn (&mut self, rhs: Self) {
+                *self = Self(self.0 $op rhs.0, self.1 $op rhs.1, self.2 $op rhs.2);
+            }
+        }
+    };
+}
+
+impl_op!(* , Mul , mul);
+impl_op!(+, Add, add);
+impl_op!(-, Sub, sub);
+impl_op!(/, Div, div);
+impl_op!(+, AddAssign, add_assign -assign);
+impl_op!(-, SubAssign, sub_assign -assign);
+impl_op!(*, MulAssign, mul_assign -assign);
+impl_op!(/, DivAssign, div_assign -assign);
+
+/// An axis-aligned bounding box made of a low corner point and a high corner point
+/// ## Gurantees
+/// Every coordinate of the first [Point3] must be less than or equal to the same coordinate of
+/// the second [Point3]
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[allow(clippy::upper_case_acronyms)]
+pub struct AABB(pub Point3, pub Point3);
+
+impl AABB {
+    /// Get the lowest corner point
+    #[inline(always)]
+    pub const fn low(&self) -> Point3 {
+        self.0
+    }
+
+    /// Get the highest corner point
+    #[inline(always)]
+    pub const fn high(&self) -> Point3 {
+        self.1
+    }
+
+    /// Return the center of this box
+    pub fn center(&self) -> Point3 {
+        Point3(
+            (self.low().x() + self.high().x()) / 2f32,
+            (self.low().y() + self.high().y()) / 2f32,
+            (self.low().z() + self.high().z()) / 2f32,
+        )
+    }
+
+    /// Get the volume of this box
+    pub fn volume(&self) -> f32 {
+        let size = self.high() - self.low();
+        size.x() * size.y() * size.z()
+    }
+
+    /// Get one eighth of this box, with bit 0 of `octant` selecting the high X half, bit 1 the high
+    /// Y half, and bit 2 the high Z half
+    pub fn octant(&self, octant: usize) -> AABB {
+        let center = self.center();
+        let pick = |bit: usize, low: f32, mid: f32, high: f32| match octant & bit != 0 {
+            true => (mid, high),
+            false => (low, mid),
+        };
+        let (lx, hx) = pick(0b001, self.low().x(), center.x(), self.high().x());
+        let (ly, hy) = pick(0b010, self.low().y(), center.y(), self.high().y());
+        let (lz, hz) = pick(0b100, self.low().z(), center.z(), self.high().z());
+        AABB(Point3(lx, ly, lz), Point3(hx, hy, hz))
+    }
+
+    /// Check if this box contains a point
+    pub fn contains(&self, point: Point3) -> bool {
+        point.x() >= self.low().x()
+            && point.y() >= self.low().y()
+            && point.z() >= self.low().z()
+            && point.x() <= self.high().x()
+            && point.y() <= self.high().y()
+            && point.z() <= self.high().z()
+    }
+
+    /// Check if one [AABB] overlaps with another
+    pub fn intersects(&self, other: AABB) -> bool {
+        self.low().x() <= other.high().x()
+            && self.high().x() >= other.low().x()
+            && self.low().y() <= other.high().y()
+            && self.high().y() >= other.low().y()
+            && self.low().z() <= other.high().z()
+            && self.high().z() >= other.low().z()
+    }
+}
+
+impl fmt::Display for Point3 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {}, {})", self.0, self.1, self.2)
+    }
+}
+impl fmt::Display for AABB {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} - {}", self.0, self.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(neighbors: Vec<(Point3, Index)>) -> Vec<Point3> {
+        let mut points = neighbors.iter().map(|(point, _)| *point).collect::<Vec<_>>();
+        points.sort_by(|this, next| this.partial_cmp(next).unwrap_or(std::cmp::Ordering::Equal));
+        points
+    }
+
+    #[test]
+    pub fn test_insert() {
+        let mut oct = Octree::new(AABB(Point3(0., 0., 0.), Point3(100., 100., 100.)));
+        oct.insert(Point3(1., 1., 1.), 1).unwrap();
+        oct.insert(Point3(5., 1., 3.), 2).unwrap();
+        oct.insert(Point3(12., 12., 12.), 3).unwrap();
+        oct.insert(Point3(80., 80., 80.), 4).unwrap();
+        assert_eq!(oct.insert(Point3(101., 0., 0.), 5), Err(5));
+
+        //(12, 12, 12) is inside the bounding cube of the sphere but outside of the sphere itself
+        let neighbors = oct.neighbors(Point3(4., 4., 4.), 10.);
+        assert_eq!(sorted(neighbors), vec![Point3(1., 1., 1.), Point3(5., 1., 3.)]);
+        assert_eq!(sorted(oct.neighbors(Point3(90., 90., 90.), 18.)), vec![Point3(80., 80., 80.)]);
+    }
+
+    #[test]
+    pub fn test_coincident_points() {
+        let mut oct = Octree::with_max_depth(AABB(Point3(0., 0., 0.), Point3(100., 100., 100.)), 6);
+        for i in 0..20 {
+            oct.insert(Point3(30., 40., 50.), i).unwrap();
+        }
+        oct.insert(Point3(70., 70., 70.), 20).unwrap();
+        assert_eq!(oct.len(), 21);
+        let found = oct.neighbors(Point3(30., 40., 50.), 1.);
+        assert_eq!(found.len(), 20);
+
+        let (pos, handle) = found[0];
+        let val = *oct.get(handle).unwrap();
+        assert_eq!(oct.remove(pos, handle), Some(val));
+        assert_eq!(oct.neighbors(Point3(30., 40., 50.), 1.).len(), 19);
+
+        //The default depth also stops splitting coincident points
+        let mut oct = Octree::new(AABB(Point3(0., 0., 0.), Point3(100., 100., 100.)));
+        oct.insert(Point3(1., 2., 3.), 0).unwrap();
+        oct.insert(Point3(1., 2., 3.), 1).unwrap();
+        assert_eq!(oct.neighbors(Point3(1., 2., 3.), 0.).len(), 2);
+    }
+
+    #[test]
+    pub fn test_remove() {
+        let mut oct = Octree::new(AABB(Point3(0., 0., 0.), Point3(100., 100., 100.)));
+        oct.insert(Point3(10., 10., 10.), 1).unwrap();
+        oct.insert(Point3(11., 10., 10.), 2).unwrap();
+
+        let (pos, handle) = oct.neighbors(Point3(11., 10., 10.), 0.5)[0];
+        assert_eq!(oct.get(handle), Some(&2));
+        assert_eq!(oct.remove(pos, handle), Some(2));
+        assert_eq!(oct.len(), 1);
+        assert_eq!(sorted(oct.neighbors(Point3(10., 10., 10.), 5.)), vec![Point3(10., 10., 10.)]);
+    }
+}
@@ -1,6 +1,7 @@
 //! A quadtree structure for efficiently storing system coordinates
 use generational_arena::{Arena, Index};
 use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, collections::BinaryHeap};
 
 /// The `Branch` struct is used in the [Branch](Node::Branch) variant of the [Node] enum,
 /// and contains a bounding box for the contained nodes and the child nodes
@@ -24,8 +25,72 @@ pub struct Branch {
 }
 
 impl Branch {
-    /// Insert the given point into the branch, returning `true` if the value was inserted
-    fn insert(&mut self, pos: Point, val: Index) -> bool {
+    /// Build a branch at the given depth directly from a set of points contained in `bb`, partitioning the
+    /// points into quadrants the same way that [insert](Branch::insert) would
+    fn build(bb: Rect, points: &mut [(Point, Index)], depth: usize, max_depth: usize) -> Self {
+        let mut children: Box<[Option<Node>; 4]> = Box::new([None, None, None, None]);
+        let mut rest = points;
+        //Quadrants are checked in the same order as insertion so points on an edge end up in the same child
+        for dir in [Dir::NW, Dir::SW, Dir::SE, Dir::NE].iter().copied() {
+            let area = dir.of(bb);
+            //Move all remaining points in this quadrant to the front of the slice
+            let mut len = 0;
+            for i in 0..rest.len() {
+                if area.contains(rest[i].0) {
+                    rest.swap(i, len);
+                    len += 1;
+                }
+            }
+            let (inside, remaining) = rest.split_at_mut(len);
+            children[dir as usize] = match inside {
+                [] => None,
+                [leaf] => Some(Node::Leaf(*leaf)),
+                _ if depth + 1 >= max_depth => Some(Node::Bucket(inside.to_vec())),
+                _ => Some(Node::Branch(Self::build(area, inside, depth + 1, max_depth))),
+            };
+            rest = remaining;
+        }
+        Self { bb, children }
+    }
+
+    /// Count the branches and leaves below this branch at the given depth, adding them to `stats`
+    fn stats(&self, depth: usize, stats: &mut TreeStats, leaf_depths: &mut usize) {
+        stats.nodes += 1;
+        for child in self.children.iter().flatten() {
+            match child {
+                Node::Branch(branch) => branch.stats(depth + 1, stats, leaf_depths),
+                Node::Leaf(_) => {
+                    stats.nodes += 1;
+                    stats.leaves += 1;
+                    stats.depth = stats.depth.max(depth + 1);
+                    *leaf_depths += depth + 1;
+                }
+                Node::Bucket(points) => {
+                    stats.nodes += 1;
+                    stats.leaves += points.len();
+                    stats.depth = stats.depth.max(depth + 1);
+                    *leaf_depths += (depth + 1) * points.len();
+                }
+            }
+        }
+    }
+
+    /// Get the maximum depth of any leaf below this branch, with children of this branch at a
+    /// depth of 1
+    fn depth(&self) -> usize {
+        self.children
+            .iter()
+            .flatten()
+            .map(|child| match child {
+                Node::Branch(branch) => branch.depth() + 1,
+                Node::Leaf(_) | Node::Bucket(_) => 1,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Insert the given point into the branch at the given depth, returning `true` if the value was inserted
+    fn insert(&mut self, pos: Point, val: Index, depth: usize, max_depth: usize) -> bool {
         if !self.bb.contains(pos) {
             return false;
         }
@@ -34,7 +99,7 @@ impl Branch {
         let nw = Dir::NW.of(self.bb);
         if nw.contains(pos) {
             match unsafe { self.children.get_unchecked_mut(Dir::NW as usize) } {
-                Some(node) => return node.insert(pos, val, nw),
+                Some(node) => node.insert(pos, val, nw, depth + 1, max_depth),
                 node @ None => {
                     *node = Some(Node::Leaf((pos, val)));
                     true
@@ -44,7 +109,7 @@ impl Branch {
             let sw = Dir::SW.of(self.bb);
             if sw.contains(pos) {
                 match unsafe { self.children.get_unchecked_mut(Dir::SW as usize) } {
-                    Some(node) => return node.insert(pos, val, sw),
+                    Some(node) => node.insert(pos, val, sw, depth + 1, max_depth),
                     node @ None => {
                         *node = Some(Node::Leaf((pos, val)));
                         true
@@ -54,7 +119,7 @@ impl Branch {
                 let se = Dir::SE.of(self.bb);
                 if se.contains(pos) {
                     match unsafe { self.children.get_unchecked_mut(Dir::SE as usize) } {
-                        Some(node) => return node.insert(pos, val, se),
+                        Some(node) => node.insert(pos, val, se, depth + 1, max_depth),
                         node @ None => {
                             *node = Some(Node::Leaf((pos, val)));
                             true
@@ -64,7 +129,7 @@ impl Branch {
                     let ne = Dir::NE.of(self.bb);
                     if ne.contains(pos) {
                         match unsafe { self.children.get_unchecked_mut(Dir::NE as usize) } {
-                            Some(node) => return node.insert(pos, val, ne),
+                            Some(node) => node.insert(pos, val, ne, depth + 1, max_depth),
                             node @ None => {
                                 *node = Some(Node::Leaf((pos, val)));
                                 true
@@ -78,17 +143,72 @@ impl Branch {
         }
     }
 
+    /// Remove the leaf with the given handle at the given point, returning `true` if the leaf was found
+    fn remove(&mut self, pos: Point, val: Index) -> bool {
+        for (dir, child) in self.children.iter_mut().enumerate() {
+            //Points on the edge of a quadrant can be contained in more than one child
+            if !Dir::from(dir as u8).of(self.bb).contains(pos) {
+                continue;
+            }
+            let removed = match child {
+                Some(Node::Branch(branch)) => branch.remove(pos, val),
+                Some(Node::Leaf((_, idx))) if *idx == val => {
+                    *child = None;
+                    true
+                }
+                Some(Node::Bucket(points)) => match points.iter().position(|(_, idx)| *idx == val) {
+                    Some(i) => {
+                        points.swap_remove(i);
+                        if points.is_empty() {
+                            *child = None;
+                        }
+                        true
+                    }
+                    None => false,
+                },
+                _ => false,
+            };
+            if removed {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Collect every leaf contained in this branch and its children
+    fn leaves(&self, leaves: &mut Vec<(Point, Index)>) {
+        for child in self.children.iter().flatten() {
+            match child {
+                Node::Branch(branch) => branch.leaves(leaves),
+                Node::Leaf(leaf) => leaves.push(*leaf),
+                Node::Bucket(points) => leaves.extend_from_slice(points),
+            }
+        }
+    }
+
+    /// Get every leaf contained in `area`
+    fn query_rect(&self, area: Rect, found: &mut Vec<(Point, Index)>) {
+        if !self.bb.intersects(area) {
+            return;
+        }
+        for child in self.children.iter().flatten() {
+            match child {
+                Node::Branch(branch) => branch.query_rect(area, found),
+                Node::Leaf((pos, idx)) => {
+                    if area.contains(*pos) {
+                        found.push((*pos, *idx))
+                    }
+                }
+                Node::Bucket(points) => found.extend(points.iter().filter(|(pos, _)| area.contains(*pos))),
+            }
+        }
+    }
+
     /// Get the neighbors within a certain radius of a point
     fn neighbors(&self, pos: Point, radius: f32, neighbors: &mut Vec<(Point, Index)>) {
         let search_bb = Rect(
-            Point(
-                (pos.x() - radius).clamp(0f32, f32::MAX),
-                (pos.y() - radius).clamp(0f32, f32::MAX),
-            ),
-            Point(
-                (pos.x() + radius).clamp(0f32, self.bb.high().x()),
-                (pos.y() + radius).clamp(0f32, self.bb.high().y()),
-            ),
+            Point(pos.x() - radius, pos.y() - radius),
+            Point(pos.x() + radius, pos.y() + radius),
         );
         //Make sure this branch actually can contain a point in the search area
         if self.bb.intersects(search_bb) {
@@ -101,6 +221,7 @@ impl Branch {
 }
 
 /// A direction for the child nodes of a [Branch]
+#[derive(Clone, Copy)]
 #[repr(u8)]
 enum Dir {
     NW = 0,
@@ -141,6 +262,9 @@ pub enum Node {
     Branch(Branch),
     /// A leaf node with position and data
     Leaf((Point, Index)),
+    /// A leaf node at the maximum depth of the tree, holding every point that reaches it instead of
+    /// splitting into a branch
+    Bucket(Vec<(Point, Index)>),
 }
 
 /// The `QuadTree` struct is used to hold a record of locations on a 2D coordinate grid
@@ -150,6 +274,18 @@ pub struct QuadTree<T> {
     arena: Arena<T>,
     /// The root node of the quad tree
     root: Branch,
+    /// The maximum depth of any leaf, past which leaves hold many points instead of splitting
+    #[serde(default = "default_max_depth")]
+    max_depth: usize,
+}
+
+/// The maximum depth of leaves in a [QuadTree] created with [new](QuadTree::new), deep enough that only
+/// nearly coincident points will share a leaf
+pub const DEFAULT_MAX_DEPTH: usize = 32;
+
+/// Get the [DEFAULT_MAX_DEPTH] for quad trees saved before their maximum depth was saved with them
+fn default_max_depth() -> usize {
+    DEFAULT_MAX_DEPTH
 }
 
 impl Node {
@@ -161,29 +297,36 @@ impl Node {
         })
     }
 
-    /// Insert a handle to type `T` into this node, either filling an empty child node or
-    /// splitting this leaf into a branch
+    /// Insert a handle to type `T` into this node at the given depth, either filling an empty child node or
+    /// splitting this leaf into a branch. Leaves at `max_depth` become buckets instead of splitting
     ///
     /// Returns `true` if the value was inserted and `false` if insertion failed
-    fn insert(&mut self, pos: Point, val: Index, area: Rect) -> bool {
+    fn insert(&mut self, pos: Point, val: Index, area: Rect, depth: usize, max_depth: usize) -> bool {
         match self {
             //We will insert the node into one of our children
-            Self::Branch(branch) => branch.insert(pos, val),
+            Self::Branch(branch) => branch.insert(pos, val, depth, max_depth),
+            //Return false if we can't contain this point
+            Self::Leaf(_) | Self::Bucket(_) if !area.contains(pos) => false,
+            Self::Leaf(old) if depth >= max_depth => {
+                *self = Self::Bucket(vec![*old, (pos, val)]);
+                true
+            }
             //We need to split into quadrants
             Self::Leaf((old_point, old_handle)) => {
-                //Return false if we can't contain this point
-                if !area.contains(pos) {
-                    return false;
-                }
                 let mut split = Self::branch(area);
-                split.insert(*old_point, *old_handle, area); //Insert the old contained value of the leaf
-                if split.insert(pos, val, area) {
+                //Insert the old contained value of the leaf
+                split.insert(*old_point, *old_handle, area, depth, max_depth);
+                if split.insert(pos, val, area, depth, max_depth) {
                     *self = split;
                     true
                 } else {
                     false
                 }
             }
+            Self::Bucket(points) => {
+                points.push((pos, val));
+                true
+            }
         }
     }
 
@@ -192,23 +335,56 @@ impl Node {
         match self {
             Self::Branch(branch) => branch.neighbors(pos, radius, neighbors),
             Self::Leaf((leaf_pos, idx)) => {
-                if leaf_pos.distance(pos) <= radius {
+                if leaf_pos.distance_sq(pos) <= radius * radius {
                     neighbors.push((*leaf_pos, *idx))
                 }
             }
+            Self::Bucket(points) => neighbors.extend(
+                points
+                    .iter()
+                    .filter(|(leaf_pos, _)| leaf_pos.distance_sq(pos) <= radius * radius),
+            ),
         }
     }
 }
 
 impl<T> QuadTree<T> {
-    /// Return a new [QuadTree] with the maximum given bounds
+    /// Return a new [QuadTree] with the maximum given bounds and a maximum depth of [DEFAULT_MAX_DEPTH]
     pub fn new(bounds: Rect) -> Self {
+        Self::with_max_depth(bounds, DEFAULT_MAX_DEPTH)
+    }
+
+    /// Return a new [QuadTree] with the maximum given bounds, whose leaves stop splitting once they are
+    /// `max_depth` levels deep. Points that reach a leaf at the maximum depth are all kept in that leaf,
+    /// which bounds how much memory clustered points can use at the cost of searching the leaf linearly
+    pub fn with_max_depth(bounds: Rect, max_depth: usize) -> Self {
         Self {
             arena: Arena::new(),
             root: Branch {
                 bb: bounds,
                 children: Box::new([None, None, None, None]),
             },
+            max_depth,
+        }
+    }
+
+    /// Build a quad tree from many points at once, skipping any points that are outside of `bounds`
+    ///
+    /// The tree is built top-down by partitioning the points into quadrants in place, instead of
+    /// descending from the root and splitting leaves for every insert. Points on the edge of a quadrant
+    /// are placed in the same quadrant that [insert](QuadTree::insert) would choose, so the tree has the
+    /// same branches and leaves as one built by inserting the points one at a time
+    pub fn from_points(bounds: Rect, points: Vec<(Point, T)>) -> Self {
+        let mut arena = Arena::with_capacity(points.len());
+        let mut handles = points
+            .into_iter()
+            .filter(|(pos, _)| bounds.contains(*pos))
+            .map(|(pos, val)| (pos, arena.insert(val)))
+            .collect::<Vec<_>>();
+        Self {
+            arena,
+            root: Branch::build(bounds, &mut handles, 0, DEFAULT_MAX_DEPTH),
+            max_depth: DEFAULT_MAX_DEPTH,
         }
     }
 
@@ -216,7 +392,7 @@ impl<T> QuadTree<T> {
     /// in this quad tree and was inserted, or `Err(val)` if it is not
     pub fn insert(&mut self, pos: Point, val: T) -> Result<(), T> {
         let handle = self.arena.insert(val);
-        match self.root.insert(pos, handle) {
+        match self.root.insert(pos, handle, 0, self.max_depth) {
             true => Ok(()),
             false => Err(self.arena.remove(handle).unwrap()),
         }
@@ -228,6 +404,220 @@ impl<T> QuadTree<T> {
         self.root.neighbors(pos, radius, &mut neighbors); //Search root for neighbors
         neighbors
     }
+
+    /// Get every value inside of `area` and its position
+    pub fn query_rect(&self, area: Rect) -> Vec<(Point, Index)> {
+        let mut found = Vec::new();
+        self.root.query_rect(area, &mut found);
+        found
+    }
+
+    /// Get the `k` values closest to `pos` and their positions, ordered from nearest to farthest
+    ///
+    /// Nodes are visited in order of their distance from `pos` using a min-heap, so only the branches
+    /// that could contain one of the nearest values are searched
+    pub fn nearest(&self, pos: Point, k: usize) -> Vec<(Point, Index)> {
+        let mut nearest = Vec::with_capacity(k);
+        if k == 0 {
+            return nearest;
+        }
+        let mut heap = BinaryHeap::new();
+        heap.push(Candidate {
+            dist: self.root.bb.distance_to(pos),
+            node: CandidateNode::Branch(&self.root),
+        });
+        while let Some(Candidate { node, .. }) = heap.pop() {
+            match node {
+                CandidateNode::Leaf(leaf_pos, handle) => {
+                    nearest.push((leaf_pos, handle));
+                    if nearest.len() == k {
+                        break;
+                    }
+                }
+                CandidateNode::Branch(branch) => {
+                    for child in branch.children.iter().flatten() {
+                        match child {
+                            Node::Branch(child) => heap.push(Candidate {
+                                dist: child.bb.distance_to(pos),
+                                node: CandidateNode::Branch(child),
+                            }),
+                            Node::Leaf(leaf) => heap.push(Candidate::leaf(*leaf, pos)),
+                            Node::Bucket(points) => heap.extend(points.iter().map(|leaf| Candidate::leaf(*leaf, pos))),
+                        }
+                    }
+                }
+            }
+        }
+        nearest
+    }
+
+    /// Remove the value with the given handle located at `pos`, returning the value if it was
+    /// contained in this quad tree
+    pub fn remove(&mut self, pos: Point, handle: Index) -> Option<T> {
+        match self.root.remove(pos, handle) {
+            true => self.arena.remove(handle),
+            false => None,
+        }
+    }
+
+    /// Get the bounds that every point in this quad tree must be contained in
+    #[inline]
+    pub fn bounds(&self) -> Rect {
+        self.root.bb
+    }
+
+    /// Shrink the bounds of the quad tree to the smallest [Rect] containing every value and rebuild the tree,
+    /// so that searches descend through fewer branches after many values were removed. Every handle stays
+    /// valid, but values outside of the new bounds can no longer be inserted. Does nothing if the quad tree
+    /// is empty
+    pub fn rebuild_tight(&mut self) {
+        let mut leaves = Vec::with_capacity(self.arena.len());
+        self.root.leaves(&mut leaves);
+        let bounds = leaves
+            .iter()
+            .map(|(pos, _)| Rect(*pos, *pos))
+            .reduce(|bounds, point| bounds.union(point));
+        if let Some(bounds) = bounds {
+            self.root = Branch::build(bounds, &mut leaves, 0, self.max_depth);
+        }
+    }
+
+    /// Get the maximum depth of any leaf in the quad tree, with children of the root at a depth of 1
+    pub fn depth(&self) -> usize {
+        self.root.depth()
+    }
+
+    /// Get the number of nodes and leaves in the quad tree and how deep the leaves are, to tell if the tree
+    /// has become unbalanced enough to rebuild
+    pub fn stats(&self) -> TreeStats {
+        let mut stats = TreeStats::default();
+        let mut leaf_depths = 0;
+        self.root.stats(0, &mut stats, &mut leaf_depths);
+        if stats.leaves > 0 {
+            stats.avg_depth = leaf_depths as f32 / stats.leaves as f32;
+        }
+        stats
+    }
+
+    /// Iterate over every value in the quad tree and its position
+    pub fn iter(&self) -> impl Iterator<Item = (Point, &T)> {
+        let mut leaves = Vec::with_capacity(self.arena.len());
+        self.root.leaves(&mut leaves);
+        leaves
+            .into_iter()
+            .map(move |(pos, handle)| (pos, &self.arena[handle]))
+    }
+
+    /// Get a reference to the value with the given handle
+    #[inline]
+    pub fn get(&self, handle: Index) -> Option<&T> {
+        self.arena.get(handle)
+    }
+
+    /// Get a mutable reference to the value with the given handle
+    #[inline]
+    pub fn get_mut(&mut self, handle: Index) -> Option<&mut T> {
+        self.arena.get_mut(handle)
+    }
+
+    /// Get the number of values contained in this quad tree
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.arena.len()
+    }
+
+    /// Check if this quad tree contains no values
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.arena.is_empty()
+    }
+}
+
+impl<T> super::SpatialIndex<T> for QuadTree<T> {
+    fn insert(&mut self, pos: Point, val: T) -> Result<(), T> {
+        QuadTree::insert(self, pos, val)
+    }
+
+    fn neighbors(&self, pos: Point, radius: f32) -> Vec<(Point, Index)> {
+        QuadTree::neighbors(self, pos, radius)
+    }
+
+    fn query_rect(&self, area: Rect) -> Vec<(Point, Index)> {
+        QuadTree::query_rect(self, area)
+    }
+
+    fn remove(&mut self, pos: Point, handle: Index) -> Option<T> {
+        QuadTree::remove(self, pos, handle)
+    }
+
+    fn get(&self, handle: Index) -> Option<&T> {
+        QuadTree::get(self, handle)
+    }
+
+    fn len(&self) -> usize {
+        QuadTree::len(self)
+    }
+
+    fn bounds(&self) -> Rect {
+        QuadTree::bounds(self)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (Point, &T)> + '_> {
+        Box::new(QuadTree::iter(self))
+    }
+}
+
+/// The shape of a [QuadTree], returned by [stats](QuadTree::stats)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TreeStats {
+    /// The number of branches and leaves in the tree, including the root branch
+    pub nodes: usize,
+    /// The number of leaves in the tree, which is the number of values
+    pub leaves: usize,
+    /// The maximum depth of any leaf, with children of the root at a depth of 1
+    pub depth: usize,
+    /// The average depth of all leaves
+    pub avg_depth: f32,
+}
+
+/// A node waiting to be searched by [nearest](QuadTree::nearest), ordered so that the binary heap
+/// pops the node closest to the search point first
+struct Candidate<'a> {
+    /// The distance from the search point to the leaf or to the closest edge of the branch
+    dist: f32,
+    node: CandidateNode<'a>,
+}
+
+enum CandidateNode<'a> {
+    Branch(&'a Branch),
+    Leaf(Point, Index),
+}
+
+impl Candidate<'_> {
+    /// Create a candidate for a leaf at the given distance from the search point
+    fn leaf((leaf_pos, handle): (Point, Index), pos: Point) -> Self {
+        Self {
+            dist: leaf_pos.distance(pos),
+            node: CandidateNode::Leaf(leaf_pos, handle),
+        }
+    }
+}
+
+impl PartialEq for Candidate<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl Eq for Candidate<'_> {}
+impl PartialOrd for Candidate<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Candidate<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.dist.total_cmp(&self.dist)
+    }
 }
 
 use std::fmt;
@@ -241,80 +631,63 @@ impl fmt::Display for Dir {
         }
     }
 }
-impl<T: fmt::Debug> QuadTree<T> {
-    /// Write the given branch to the formatter
-    fn write_branch(
-        &self,
-        branch: &Branch,
-        f: &mut fmt::Formatter<'_>,
-        spaceno: u16,
-    ) -> fmt::Result {
+/// The number of decimal places that coordinates are written with when a [QuadTree] is formatted
+/// without a precision, which can be given like `{:.3}`
+pub const DEFAULT_PRECISION: usize = 1;
+
+/// The contents of a child of a branch, written after the child's label when formatting a [QuadTree]
+enum Line {
+    Branch,
+    /// Every point in a leaf, which only has more than one point if the leaf is a bucket
+    Leaf(Vec<(Point, Index)>),
+    Empty,
+}
+
+impl<T> QuadTree<T> {
+    /// Collect the depth, label, and contents of every child below the given branch in the order they are written
+    fn lines(branch: &Branch, depth: usize, precision: usize, lines: &mut Vec<(usize, String, Line)>) {
         for (dir, child) in branch.children.iter().enumerate() {
-            for _ in 0..spaceno {
-                write!(f, " ")?
-            }
-            write!(
-                f,
-                "[{}]{}: ",
-                Dir::from(dir as u8).of(branch.bb),
-                Dir::from(dir as u8)
-            )?;
+            let dir = Dir::from(dir as u8);
+            let label = format!("{} [{:.*}]", dir, precision, dir.of(branch.bb));
             match child {
-                Some(child) => match child {
-                    Node::Branch(other) => {
-                        writeln!(f)?;
-                        self.write_branch(other, f, spaceno + 1)?
-                    }
-                    Node::Leaf((pos, data)) => {
-                        write!(f, "{} [{:?}]", pos, self.arena[*data])?;
-                    }
-                },
-                None => {
-                    for _ in 0..spaceno {
-                        write!(f, " ")?
-                    }
-                    write!(f, "<none>")?;
+                Some(Node::Branch(other)) => {
+                    lines.push((depth, label, Line::Branch));
+                    Self::lines(other, depth + 1, precision, lines);
                 }
+                Some(Node::Leaf(leaf)) => lines.push((depth, label, Line::Leaf(vec![*leaf]))),
+                Some(Node::Bucket(points)) => lines.push((depth, label, Line::Leaf(points.clone()))),
+                None => lines.push((depth, label, Line::Empty)),
             }
-            writeln!(f)?;
         }
-        Ok(())
     }
-}
-impl<T: fmt::Display> QuadTree<T> {
-    /// Write the given branch to the formatter
-    fn write_branch_display(
+
+    /// Write every node of the tree to the formatter, indenting children one space past their parent and
+    /// padding labels so that the contents of every node start in the same column
+    fn write_tree(
         &self,
-        branch: &Branch,
         f: &mut fmt::Formatter<'_>,
-        spaceno: u16,
+        write_val: impl Fn(&T, &mut fmt::Formatter<'_>) -> fmt::Result,
     ) -> fmt::Result {
-        for (dir, child) in branch.children.iter().enumerate() {
-            for _ in 0..spaceno {
-                write!(f, " ")?
-            }
-            write!(
-                f,
-                "[{}]{}: ",
-                Dir::from(dir as u8).of(branch.bb),
-                Dir::from(dir as u8)
-            )?;
-            match child {
-                Some(child) => match child {
-                    Node::Branch(other) => {
-                        writeln!(f)?;
-                        self.write_branch_display(other, f, spaceno + 1)?
-                    }
-                    Node::Leaf((pos, data)) => {
-                        write!(f, "{} [{}]", pos, self.arena[*data])?;
-                    }
-                },
-                None => {
-                    for _ in 0..spaceno {
-                        write!(f, " ")?
+        let precision = f.precision().unwrap_or(DEFAULT_PRECISION);
+        let mut lines = Vec::new();
+        Self::lines(&self.root, 0, precision, &mut lines);
+        let width = lines.iter().map(|(depth, label, _)| depth + label.len()).max().unwrap_or(0);
+
+        for (depth, label, line) in lines {
+            write!(f, "{:indent$}{:<width$}: ", "", label, indent = depth, width = width - depth)?;
+            match line {
+                Line::Branch => (),
+                Line::Leaf(points) => {
+                    for (i, (pos, data)) in points.into_iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        write!(f, "{:.*} [", precision, pos)?;
+                        write_val(&self.arena[data], f)?;
+                        write!(f, "]")?;
                     }
-                    write!(f, "<none>")?;
                 }
+                Line::Empty => write!(f, "<none>")?,
             }
             writeln!(f)?;
         }
@@ -323,12 +696,12 @@ impl<T: fmt::Display> QuadTree<T> {
 }
 impl<T: fmt::Debug> fmt::Debug for QuadTree<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.write_branch(&self.root, f, 0)
+        self.write_tree(f, |val, f| write!(f, "{:?}", val))
     }
 }
 impl<T: fmt::Display> fmt::Display for QuadTree<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.write_branch_display(&self.root, f, 0)
+        self.write_tree(f, |val, f| write!(f, "{}", val))
     }
 }
 
@@ -349,7 +722,14 @@ impl Point {
 
     /// Return the distance between this point and another point
     pub fn distance(&self, other: Self) -> f32 {
-        (((other.0 - self.0).powi(2)) + ((other.1 - self.1).powi(2))).sqrt()
+        self.distance_sq(other).sqrt()
+    }
+
+    /// Return the squared distance between this point and another point, which is cheaper to compute
+    /// than [distance](Point::distance) when only comparing distances
+    #[inline]
+    pub fn distance_sq(&self, other: Self) -> f32 {
+        ((other.0 - self.0).powi(2)) + ((other.1 - self.1).powi(2))
     }
 }
 
@@ -380,6 +760,17 @@ impl_op!(-, SubAssign, sub_assign -assign);
 impl_op!(*, MulAssign, mul_assign -assign);
 impl_op!(/, DivAssign, div_assign -assign);
 
+impl From<[f32; 2]> for Point {
+    fn from([x, y]: [f32; 2]) -> Self {
+        Self(x, y)
+    }
+}
+impl From<Point> for [f32; 2] {
+    fn from(point: Point) -> Self {
+        [point.0, point.1]
+    }
+}
+
 /// A rectangle made of a low corner point and a high corner point
 /// ## Gurantees
 /// The first [Point] must always be lower and further left than the second
@@ -426,11 +817,11 @@ impl Rect {
     }
 
     /// Get the north western quarter of this rectangle
+    ///
+    /// All four quarters are split at [center](Rect::center), so they always tile this rectangle exactly
     pub fn nw(&self) -> Rect {
-        Rect(
-            Point(self.low().x(), self.low().y() + (self.height() / 2f32)),
-            Point(self.high().x() - (self.len() / 2f32), self.high().y()),
-        )
+        let center = self.center();
+        Rect(Point(self.low().x(), center.y()), Point(center.x(), self.high().y()))
     }
 
     /// Get the north eastern quarter of this rectangle
@@ -441,11 +832,7 @@ impl Rect {
     /// Get the south eastern quarter of this rectangle
     pub fn se(&self) -> Rect {
         let center = self.center();
-        let half_height = self.height() / 2f32;
-        Rect(
-            Point(center.x(), center.y() - half_height),
-            Point(self.high().x(), self.high().y() - half_height),
-        )
+        Rect(Point(center.x(), self.low().y()), Point(self.high().x(), center.y()))
     }
 
     /// Get the south western quarter of this rectangle
@@ -481,20 +868,64 @@ impl Rect {
             && point.y() <= self.high().y()
     }
 
+    /// Get the closest point in this rectangle to the given point, which is the point itself if the
+    /// rectangle contains it
+    pub fn clamp(&self, point: Point) -> Point {
+        Point(
+            point.x().clamp(self.low().x(), self.high().x()),
+            point.y().clamp(self.low().y(), self.high().y()),
+        )
+    }
+
+    /// Get the distance from a point to the closest point in this rectangle, which is zero if the
+    /// rectangle contains the point
+    pub fn distance_to(&self, point: Point) -> f32 {
+        self.clamp(point).distance(point)
+    }
+
+    /// Get the smallest rectangle containing both this rectangle and another
+    pub fn union(&self, other: Rect) -> Rect {
+        Rect(
+            Point(self.low().x().min(other.low().x()), self.low().y().min(other.low().y())),
+            Point(self.high().x().max(other.high().x()), self.high().y().max(other.high().y())),
+        )
+    }
+
     /// Check if one [Rect] intersects with another
     pub fn intersects(&self, other: Rect) -> bool {
-        self.contains(other.0) || self.contains(other.1)
+        self.low().x() <= other.high().x()
+            && self.high().x() >= other.low().x()
+            && self.low().y() <= other.high().y()
+            && self.high().y() >= other.low().y()
+    }
+}
+
+impl From<[[f32; 2]; 2]> for Rect {
+    /// Create a rectangle from its low and high corners, which must already be ordered like in [Rect::new]
+    fn from([low, high]: [[f32; 2]; 2]) -> Self {
+        Self(low.into(), high.into())
+    }
+}
+impl From<Rect> for [[f32; 2]; 2] {
+    fn from(rect: Rect) -> Self {
+        [rect.0.into(), rect.1.into()]
     }
 }
 
 impl fmt::Display for Point {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "({}, {})", self.0, self.1)
+        match f.precision() {
+            Some(precision) => write!(f, "({:.*}, {:.*})", precision, self.0, precision, self.1),
+            None => write!(f, "({}, {})", self.0, self.1),
+        }
     }
 }
 impl fmt::Display for Rect {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} - {}", self.0, self.1)
+        match f.precision() {
+            Some(precision) => write!(f, "{:.*} - {:.*}", precision, self.0, precision, self.1),
+            None => write!(f, "{} - {}", self.0, self.1),
+        }
     }
 }
 
@@ -515,4 +946,329 @@ mod tests {
         neighbors.sort_by(|this, next| this.partial_cmp(next).unwrap_or(std::cmp::Ordering::Equal));
         assert_eq!(neighbors, vec![Point(0., 1.), Point(5., 1.)]);
     }
+
+    #[test]
+    pub fn test_negative_neighbors() {
+        let mut quad = QuadTree::new(Rect::new(Point(-100., -100.), Point(100., 100.)));
+        quad.insert(Point(-50., -50.), 1).unwrap();
+        quad.insert(Point(-45., -52.), 2).unwrap();
+        quad.insert(Point(50., 50.), 3).unwrap();
+        let mut found = quad
+            .neighbors(Point(-48., -50.), 5.)
+            .into_iter()
+            .filter_map(|(_, handle)| quad.get(handle).copied())
+            .collect::<Vec<_>>();
+        found.sort_unstable();
+        assert_eq!(found, vec![1, 2]);
+
+        //A tree entirely below zero must search without clamping its bounds
+        let mut quad = QuadTree::new(Rect::new(Point(-100., -100.), Point(-10., -10.)));
+        quad.insert(Point(-20., -30.), 4).unwrap();
+        quad.insert(Point(-90., -90.), 5).unwrap();
+        let found = quad.neighbors(Point(-22., -30.), 3.);
+        assert_eq!(found.len(), 1);
+        assert_eq!(quad.get(found[0].1), Some(&4));
+        assert!(quad.neighbors(Point(-50., -50.), 10.).is_empty());
+    }
+
+    #[test]
+    pub fn test_nearest() {
+        let bounds = Rect::new(Point(0., 0.), Point(100., 100.));
+        let points = (0..20)
+            .flat_map(|x| (0..20).map(move |y| Point(x as f32 * 4.7 + 2., y as f32 * 4.3 + 1.5)))
+            .collect::<Vec<_>>();
+        let quad = QuadTree::from_points(bounds, points.iter().map(|pos| (*pos, *pos)).collect());
+
+        for target in [Point(0., 0.), Point(50.3, 47.9), Point(99., 3.), Point(150., 150.)].iter().copied() {
+            let mut expected = points.clone();
+            expected.sort_by(|a, b| a.distance(target).total_cmp(&b.distance(target)));
+            let found = quad
+                .nearest(target, 5)
+                .into_iter()
+                .map(|(pos, _)| pos.distance(target))
+                .collect::<Vec<_>>();
+            let expected = expected[..5].iter().map(|pos| pos.distance(target)).collect::<Vec<_>>();
+            assert_eq!(found, expected);
+        }
+        assert_eq!(quad.nearest(Point(1., 1.), 1000).len(), points.len());
+        assert!(QuadTree::<()>::new(bounds).nearest(Point(1., 1.), 1).is_empty());
+    }
+
+    #[test]
+    pub fn test_distance_sq() {
+        let points = [Point(0., 0.), Point(3., 4.), Point(-2.5, 7.25), Point(100., 0.5)];
+        for a in points.iter() {
+            for b in points.iter() {
+                let distance = a.distance(*b);
+                assert!((a.distance_sq(*b) - distance * distance).abs() <= 1e-3 * distance * distance);
+            }
+        }
+        assert_eq!(Point(0., 0.).distance_sq(Point(3., 4.)), 25.);
+
+        //Neighbors found with squared distances must match a brute force search with distances
+        let bounds = Rect::new(Point(0., 0.), Point(100., 100.));
+        let points = (0..25)
+            .flat_map(|x| (0..25).map(move |y| Point(x as f32 * 3.9 + 0.3, y as f32 * 3.7 + 1.1)))
+            .collect::<Vec<_>>();
+        let quad = QuadTree::from_points(bounds, points.iter().map(|pos| (*pos, *pos)).collect());
+        for (target, radius) in [(Point(50., 50.), 10.), (Point(3., 97.), 25.), (Point(20.1, 9.7), 3.9)].iter().copied() {
+            let mut found = quad
+                .neighbors(target, radius)
+                .into_iter()
+                .map(|(pos, _)| pos)
+                .collect::<Vec<_>>();
+            let mut expected = points
+                .iter()
+                .copied()
+                .filter(|pos| pos.distance(target) <= radius)
+                .collect::<Vec<_>>();
+            found.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    pub fn test_query_rect() {
+        let mut quad = QuadTree::new(Rect::new(Point(0., 0.), Point(100., 100.)));
+        for (i, pos) in [Point(10., 10.), Point(20., 30.), Point(25., 25.), Point(60., 70.), Point(90., 5.)].iter().enumerate() {
+            quad.insert(*pos, i).unwrap();
+        }
+        let mut found = quad
+            .query_rect(Rect::new(Point(5., 5.), Point(30., 30.)))
+            .into_iter()
+            .map(|(_, handle)| *quad.get(handle).unwrap())
+            .collect::<Vec<_>>();
+        found.sort_unstable();
+        assert_eq!(found, vec![0, 1, 2]);
+        assert!(quad.query_rect(Rect::new(Point(30., 80.), Point(50., 99.))).is_empty());
+    }
+
+    #[test]
+    pub fn test_quadrants() {
+        let rects = [
+            Rect::new(Point(0., 0.), Point(100., 100.)),
+            Rect::new(Point(0.1, 0.3), Point(7.7, 13.9)),
+            Rect::new(Point(-1234.567, 89.01), Point(3.3, 4567.89)),
+        ];
+        for rect in rects.iter() {
+            let quadrants = [rect.nw(), rect.ne(), rect.se(), rect.sw()];
+            //The quadrants share edges exactly at the center of the parent
+            let center = rect.center();
+            for quadrant in quadrants.iter() {
+                assert!(quadrant.low().x() == rect.low().x() || quadrant.low().x() == center.x());
+                assert!(quadrant.high().x() == rect.high().x() || quadrant.high().x() == center.x());
+                assert!(quadrant.low().y() == rect.low().y() || quadrant.low().y() == center.y());
+                assert!(quadrant.high().y() == rect.high().y() || quadrant.high().y() == center.y());
+            }
+            //The union of the quadrants is the parent
+            let low = quadrants.iter().fold(rect.high(), |low, q| Point(low.x().min(q.low().x()), low.y().min(q.low().y())));
+            let high = quadrants.iter().fold(rect.low(), |high, q| Point(high.x().max(q.high().x()), high.y().max(q.high().y())));
+            assert_eq!(Rect(low, high), *rect);
+            //No two quadrants overlap by more than a shared edge
+            for (i, a) in quadrants.iter().enumerate() {
+                for b in quadrants[i + 1..].iter() {
+                    let overlap_x = a.high().x().min(b.high().x()) - a.low().x().max(b.low().x());
+                    let overlap_y = a.high().y().min(b.high().y()) - a.low().y().max(b.low().y());
+                    assert!(overlap_x <= 0. || overlap_y <= 0., "{} overlaps {}", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_stats() {
+        let mut quad = QuadTree::new(Rect::new(Point(0., 0.), Point(100., 100.)));
+        assert_eq!(quad.stats(), TreeStats { nodes: 1, ..Default::default() });
+        for pos in [Point(10., 10.), Point(90., 90.), Point(10., 90.), Point(90., 10.)] {
+            quad.insert(pos, ()).unwrap();
+        }
+        assert_eq!(quad.stats(), TreeStats { nodes: 5, leaves: 4, depth: 1, avg_depth: 1. });
+
+        //Splits the quadrant containing (10, 10) twice before the points are in different quadrants
+        quad.insert(Point(20., 20.), ()).unwrap();
+        let stats = quad.stats();
+        assert_eq!(stats, TreeStats { nodes: 8, leaves: 5, depth: 3, avg_depth: 9. / 5. });
+        assert_eq!(stats.depth, quad.depth());
+    }
+
+    #[test]
+    pub fn test_format_precision() {
+        let mut quad = QuadTree::new(Rect::new(Point(0., 0.), Point(100., 100.)));
+        quad.insert(Point(10.123, 20.456), 1).unwrap();
+        quad.insert(Point(12.5, 21.25), 2).unwrap();
+        quad.insert(Point(75.75, 60.), 3).unwrap();
+
+        let printed = format!("{}", quad);
+        assert!(printed.contains("(10.1, 20.5) [1]"), "{}", printed);
+        assert!(printed.contains("(75.8, 60.0) [3]"), "{}", printed);
+        assert!(printed.contains("[(0.0, 0.0) - (50.0, 50.0)]"), "{}", printed);
+        let printed = format!("{:.2}", quad);
+        assert!(printed.contains("(10.12, 20.46) [1]"), "{}", printed);
+        assert!(!printed.contains("10.123"), "{}", printed);
+        assert_eq!(format!("{:.0?}", quad).lines().count(), printed.lines().count());
+
+        //The contents of every node start in the same column no matter how deep the node is
+        let columns = printed.lines().map(|line| line.find(':')).collect::<Vec<_>>();
+        assert!(columns.windows(2).all(|pair| pair[0].is_some() && pair[0] == pair[1]), "{}", printed);
+        assert!(printed.lines().any(|line| line.starts_with(' ')), "{}", printed);
+    }
+
+    #[test]
+    pub fn test_array_conversion() {
+        let point = Point(1.5, -2.25);
+        let array: [f32; 2] = point.into();
+        assert_eq!(array, [1.5, -2.25]);
+        assert_eq!(Point::from(array), point);
+
+        let rect = Rect::new(Point(-3., 4.), Point(10.5, 20.));
+        let array: [[f32; 2]; 2] = rect.into();
+        assert_eq!(array, [[-3., 4.], [10.5, 20.]]);
+        assert_eq!(Rect::from(array), rect);
+    }
+
+    #[test]
+    pub fn test_intersects() {
+        let rect = Rect::new(Point(10., 10.), Point(20., 20.));
+        //Overlapping in a cross shape with no corners contained in the other rect
+        assert!(rect.intersects(Rect::new(Point(5., 12.), Point(25., 18.))));
+        assert!(rect.intersects(Rect::new(Point(0., 0.), Point(100., 100.))));
+        assert!(rect.intersects(Rect::new(Point(15., 15.), Point(30., 30.))));
+        assert!(!rect.intersects(Rect::new(Point(21., 0.), Point(30., 30.))));
+    }
+
+    #[test]
+    pub fn test_from_points() {
+        let bounds = Rect::new(Point(0., 0.), Point(100., 100.));
+        let points = (0..32)
+            .flat_map(|x| (0..32).map(move |y| Point(x as f32 * 3. + 1., y as f32 * 3. + 1.)))
+            .collect::<Vec<_>>();
+        let mut bulk = points.iter().map(|pos| (*pos, *pos)).collect::<Vec<_>>();
+        bulk.push((Point(150., 150.), Point(150., 150.)));
+        let bulk = QuadTree::from_points(bounds, bulk);
+
+        let mut incremental = QuadTree::new(bounds);
+        for pos in points.iter().rev() {
+            incremental.insert(*pos, *pos).unwrap();
+        }
+
+        assert_eq!(bulk.len(), points.len());
+        for pos in points.iter() {
+            let found = bulk.neighbors(*pos, 0.5);
+            assert_eq!(found.len(), 1);
+            assert_eq!(bulk.get(found[0].1), Some(pos));
+        }
+        //A 32x32 grid needs at least 5 levels of subdivision to seperate every point
+        assert!(bulk.depth() <= 8, "depth is {}", bulk.depth());
+        assert_eq!(bulk.depth(), incremental.depth());
+    }
+
+    #[test]
+    pub fn test_rebuild_tight() {
+        let mut quad = QuadTree::new(Rect::new(Point(0., 0.), Point(1000., 1000.)));
+        for x in 0..20 {
+            for y in 0..20 {
+                quad.insert(Point(x as f32 * 50. + 1., y as f32 * 50. + 1.), (x, y)).unwrap();
+            }
+        }
+        //Remove everything but a small cluster of points
+        for (pos, handle) in quad.query_rect(Rect::new(Point(0., 0.), Point(1000., 1000.))) {
+            if !Rect::new(Point(100., 200.), Point(260., 310.)).contains(pos) {
+                quad.remove(pos, handle).unwrap();
+            }
+        }
+        let remaining = quad.neighbors(Point(500., 500.), 1000.);
+        let mut search = quad.neighbors(Point(150., 250.), 60.);
+        let before = quad.depth();
+
+        quad.rebuild_tight();
+        assert_eq!(quad.bounds(), Rect::new(Point(101., 201.), Point(251., 301.)));
+        assert!(quad.depth() < before, "depth {} is not less than {}", quad.depth(), before);
+        assert_eq!(quad.len(), remaining.len());
+        for (pos, handle) in remaining.iter() {
+            let (x, y) = *quad.get(*handle).unwrap();
+            assert_eq!(Point(x as f32 * 50. + 1., y as f32 * 50. + 1.), *pos);
+        }
+        let mut after = quad.neighbors(Point(150., 250.), 60.);
+        after.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        search.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        assert_eq!(after, search);
+
+        let mut empty = QuadTree::<()>::new(Rect::new(Point(0., 0.), Point(10., 10.)));
+        empty.rebuild_tight();
+        assert_eq!(empty.bounds(), Rect::new(Point(0., 0.), Point(10., 10.)));
+    }
+
+    #[test]
+    pub fn test_from_points_matches_insert() {
+        let bounds = Rect::new(Point(0., 0.), Point(100., 100.));
+        //A grid that puts points on the edges of quadrants at every level, plus some points inside of them
+        let points = (0..=16)
+            .flat_map(|x| (0..=16).map(move |y| Point(x as f32 * 6.25, y as f32 * 6.25)))
+            .chain((0..40).map(|i| Point(i as f32 * 2.3 + 1.1, 97.7 - i as f32 * 2.1)))
+            .collect::<Vec<_>>();
+        let bulk = QuadTree::from_points(bounds, points.iter().enumerate().map(|(i, pos)| (*pos, i)).collect());
+        let mut inserted = QuadTree::new(bounds);
+        for (i, pos) in points.iter().enumerate() {
+            inserted.insert(*pos, i).unwrap();
+        }
+        assert_eq!(bulk.stats(), inserted.stats());
+        assert_eq!(bulk.to_string(), inserted.to_string());
+    }
+
+    #[test]
+    pub fn test_max_depth() {
+        let bounds = Rect::new(Point(0., 0.), Point(100., 100.));
+        let mut quad = QuadTree::with_max_depth(bounds, 4);
+        //Points far closer together than a leaf at the maximum depth, including exact duplicates
+        let points = (0..200)
+            .map(|i| Point(50.001 + (i % 10) as f32 * 1e-5, 50.001 + (i / 10) as f32 * 1e-5))
+            .chain([Point(50.001, 50.001), Point(50.001, 50.001), Point(10., 10.)])
+            .collect::<Vec<_>>();
+        for (i, pos) in points.iter().enumerate() {
+            quad.insert(*pos, i).unwrap();
+        }
+        assert_eq!(quad.len(), points.len());
+        assert_eq!(quad.depth(), 4);
+        assert_eq!(quad.stats().leaves, points.len());
+
+        let mut found = quad
+            .neighbors(Point(50.001, 50.001), 0.01)
+            .into_iter()
+            .map(|(_, handle)| *quad.get(handle).unwrap())
+            .collect::<Vec<_>>();
+        found.sort_unstable();
+        assert_eq!(found, (0..points.len() - 1).collect::<Vec<_>>());
+        assert_eq!(quad.query_rect(Rect::new(Point(50., 50.), Point(51., 51.))).len(), points.len() - 1);
+        assert_eq!(quad.nearest(Point(50.001, 50.001), points.len()).len(), points.len());
+
+        //Removing from a bucket leaves the rest of its points
+        let (pos, handle) = quad.neighbors(Point(50.001, 50.001), 0.).into_iter().next().unwrap();
+        assert!(quad.remove(pos, handle).is_some());
+        assert_eq!(quad.neighbors(Point(50.001, 50.001), 0.01).len(), points.len() - 2);
+
+        let bulk = QuadTree::from_points(bounds, points.iter().map(|pos| (*pos, ())).collect());
+        assert_eq!(bulk.len(), points.len());
+        assert_eq!(bulk.neighbors(Point(50.001, 50.001), 0.01).len(), points.len() - 1);
+    }
+
+    #[test]
+    pub fn test_remove() {
+        let mut quad = QuadTree::new(Rect::new(Point(0., 0.), Point(100., 100.)));
+        quad.insert(Point(10., 10.), 1).unwrap();
+        quad.insert(Point(12., 12.), 2).unwrap();
+        quad.insert(Point(50., 50.), 3).unwrap();
+
+        let (pos, handle) = quad
+            .neighbors(Point(12., 12.), 0.5)
+            .into_iter()
+            .next()
+            .unwrap();
+        assert_eq!(quad.get(handle), Some(&2));
+        assert_eq!(quad.remove(pos, handle), Some(2));
+        assert_eq!(quad.remove(pos, handle), None);
+        assert_eq!(quad.len(), 2);
+        assert!(quad.neighbors(Point(12., 12.), 0.5).is_empty());
+        assert_eq!(quad.neighbors(Point(10., 10.), 0.5).len(), 1);
+    }
 }
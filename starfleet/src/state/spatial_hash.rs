@@ -0,0 +1,274 @@
+//! A spatial hash grid for storing positions, an alternative to the [QuadTree](super::QuadTree) for
+//! densely and uniformly populated areas
+use generational_arena::{Arena, Index};
+use std::collections::HashMap;
+
+use super::{Point, Rect, SpatialIndex};
+
+/// The `SpatialHash` struct buckets points into square cells of a fixed size, so inserting a point
+/// never needs to descend a tree and searching only visits the cells overlapping the search area
+///
+/// Searches are fastest when the cell size is close to the usual search radius
+#[derive(Debug)]
+pub struct SpatialHash<T> {
+    /// The bounds that every point must be contained in
+    bounds: Rect,
+    /// The length of the sides of every cell
+    cell: f32,
+    /// The points and handles in every cell that contains at least one point
+    cells: HashMap<(i64, i64), Vec<(Point, Index)>>,
+    /// The arena that all values are stored in
+    arena: Arena<T>,
+}
+
+impl<T> SpatialHash<T> {
+    /// Create a new empty spatial hash holding points inside of `bounds` in square cells with sides of
+    /// length `cell`
+    pub fn new(bounds: Rect, cell: f32) -> Self {
+        debug_assert!(cell > 0., "Spatial hash cells must have a positive size");
+        Self {
+            bounds,
+            cell,
+            cells: HashMap::new(),
+            arena: Arena::new(),
+        }
+    }
+
+    /// Get the cell that contains a point
+    #[inline]
+    fn cell_of(&self, pos: Point) -> (i64, i64) {
+        ((pos.x() / self.cell).floor() as i64, (pos.y() / self.cell).floor() as i64)
+    }
+
+    /// Insert a given value and return `Ok(())` if the point is inside of this spatial hash's bounds and
+    /// was inserted, or `Err(val)` if it is not
+    pub fn insert(&mut self, pos: Point, val: T) -> Result<(), T> {
+        if !self.bounds.contains(pos) {
+            return Err(val);
+        }
+        let handle = self.arena.insert(val);
+        let cell = self.cell_of(pos);
+        self.cells.entry(cell).or_default().push((pos, handle));
+        Ok(())
+    }
+
+    /// Get every non-empty cell overlapping the area between `low` and `high`, skipping cells outside
+    /// of the bounds
+    fn cells_in(&self, low: Point, high: Point) -> Vec<&[(Point, Index)]> {
+        let (low_x, low_y) = self.cell_of(self.bounds.clamp(low));
+        let (high_x, high_y) = self.cell_of(self.bounds.clamp(high));
+        let area = (high_x - low_x + 1).saturating_mul(high_y - low_y + 1);
+        //Checking every occupied cell is faster than looking up every cell in an area larger than that
+        if area > self.cells.len() as i64 {
+            self.cells
+                .iter()
+                .filter(|((x, y), _)| (low_x..=high_x).contains(x) && (low_y..=high_y).contains(y))
+                .map(|(_, cell)| cell.as_slice())
+                .collect()
+        } else {
+            (low_x..=high_x)
+                .flat_map(|x| (low_y..=high_y).filter_map(move |y| self.cells.get(&(x, y))))
+                .map(Vec::as_slice)
+                .collect()
+        }
+    }
+
+    /// Get a list of all neighbors by searching in a circle around a point
+    pub fn neighbors(&self, pos: Point, radius: f32) -> Vec<(Point, Index)> {
+        let low = Point(pos.x() - radius, pos.y() - radius);
+        let high = Point(pos.x() + radius, pos.y() + radius);
+        self.cells_in(low, high)
+            .into_iter()
+            .flat_map(|cell| cell.iter().filter(|(point, _)| point.distance_sq(pos) <= radius * radius))
+            .copied()
+            .collect()
+    }
+
+    /// Get every value inside of `area` and its position
+    pub fn query_rect(&self, area: Rect) -> Vec<(Point, Index)> {
+        self.cells_in(area.low(), area.high())
+            .into_iter()
+            .flat_map(|cell| cell.iter().filter(|(point, _)| area.contains(*point)))
+            .copied()
+            .collect()
+    }
+
+    /// Remove the value with the given handle located at `pos`, returning the value if it was
+    /// contained in this spatial hash
+    pub fn remove(&mut self, pos: Point, handle: Index) -> Option<T> {
+        let key = self.cell_of(pos);
+        let cell = self.cells.get_mut(&key)?;
+        let idx = cell.iter().position(|(_, other)| *other == handle)?;
+        cell.swap_remove(idx);
+        if cell.is_empty() {
+            self.cells.remove(&key);
+        }
+        self.arena.remove(handle)
+    }
+
+    /// Get the bounds that every point in this spatial hash must be contained in
+    #[inline]
+    pub fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    /// Get the length of the sides of every cell
+    #[inline]
+    pub fn cell_size(&self) -> f32 {
+        self.cell
+    }
+
+    /// Iterate over every value in the spatial hash and its position
+    pub fn iter(&self) -> impl Iterator<Item = (Point, &T)> {
+        self.cells
+            .values()
+            .flatten()
+            .map(move |(pos, handle)| (*pos, &self.arena[*handle]))
+    }
+
+    /// Get a reference to the value with the given handle
+    #[inline]
+    pub fn get(&self, handle: Index) -> Option<&T> {
+        self.arena.get(handle)
+    }
+
+    /// Get a mutable reference to the value with the given handle
+    #[inline]
+    pub fn get_mut(&mut self, handle: Index) -> Option<&mut T> {
+        self.arena.get_mut(handle)
+    }
+
+    /// Get the number of values contained in this spatial hash
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.arena.len()
+    }
+
+    /// Check if this spatial hash contains no values
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.arena.is_empty()
+    }
+}
+
+impl<T> SpatialIndex<T> for SpatialHash<T> {
+    fn insert(&mut self, pos: Point, val: T) -> Result<(), T> {
+        SpatialHash::insert(self, pos, val)
+    }
+
+    fn neighbors(&self, pos: Point, radius: f32) -> Vec<(Point, Index)> {
+        SpatialHash::neighbors(self, pos, radius)
+    }
+
+    fn query_rect(&self, area: Rect) -> Vec<(Point, Index)> {
+        SpatialHash::query_rect(self, area)
+    }
+
+    fn remove(&mut self, pos: Point, handle: Index) -> Option<T> {
+        SpatialHash::remove(self, pos, handle)
+    }
+
+    fn get(&self, handle: Index) -> Option<&T> {
+        SpatialHash::get(self, handle)
+    }
+
+    fn len(&self) -> usize {
+        SpatialHash::len(self)
+    }
+
+    fn bounds(&self) -> Rect {
+        SpatialHash::bounds(self)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (Point, &T)> + '_> {
+        Box::new(SpatialHash::iter(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::QuadTree;
+
+    /// Get the sorted positions of every neighbor found by any spatial index
+    fn sorted_neighbors<T>(index: &impl SpatialIndex<T>, pos: Point, radius: f32) -> Vec<Point> {
+        let mut neighbors = index
+            .neighbors(pos, radius)
+            .into_iter()
+            .map(|(point, _)| point)
+            .collect::<Vec<_>>();
+        neighbors.sort_by(|this, next| this.partial_cmp(next).unwrap_or(std::cmp::Ordering::Equal));
+        neighbors
+    }
+
+    #[test]
+    pub fn test_insert() {
+        let mut hash = SpatialHash::new(Rect::new(Point(0., 0.), Point(100., 100.)), 10.);
+        assert_eq!(hash.insert(Point(0., 1.), 100), Ok(()));
+        hash.insert(Point(5., 1.), 200).unwrap();
+        hash.insert(Point(57., 57.), 1231).unwrap();
+        assert_eq!(hash.insert(Point(-1., 50.), 5), Err(5));
+        assert_eq!(sorted_neighbors(&hash, Point(13., 10.), 16.), vec![Point(0., 1.), Point(5., 1.)]);
+    }
+
+    #[test]
+    pub fn test_remove() {
+        let mut hash = SpatialHash::new(Rect::new(Point(0., 0.), Point(100., 100.)), 10.);
+        hash.insert(Point(10., 10.), 1).unwrap();
+        hash.insert(Point(12., 12.), 2).unwrap();
+        hash.insert(Point(50., 50.), 3).unwrap();
+
+        let (pos, handle) = hash
+            .neighbors(Point(12., 12.), 0.5)
+            .into_iter()
+            .next()
+            .unwrap();
+        assert_eq!(hash.get(handle), Some(&2));
+        assert_eq!(hash.remove(pos, handle), Some(2));
+        assert_eq!(hash.remove(pos, handle), None);
+        assert_eq!(hash.len(), 2);
+        assert!(hash.neighbors(Point(12., 12.), 0.5).is_empty());
+        assert_eq!(hash.neighbors(Point(10., 10.), 0.5).len(), 1);
+    }
+
+    #[test]
+    pub fn test_large_radius() {
+        let mut hash = SpatialHash::new(Rect::new(Point(-100., -100.), Point(100., 100.)), 1.);
+        hash.insert(Point(-90., 80.), 1).unwrap();
+        hash.insert(Point(60., -20.), 2).unwrap();
+        assert_eq!(hash.neighbors(Point(0., 0.), f32::INFINITY).len(), 2);
+        assert_eq!(hash.neighbors(Point(0., 0.), f32::MAX).len(), 2);
+        assert_eq!(hash.neighbors(Point(-90., 80.), 1000.).len(), 2);
+        assert_eq!(sorted_neighbors(&hash, Point(-85., 80.), 6.), vec![Point(-90., 80.)]);
+        assert_eq!(hash.query_rect(Rect::new(Point(f32::MIN, f32::MIN), Point(f32::MAX, f32::MAX))).len(), 2);
+    }
+
+    #[test]
+    pub fn test_matches_quadtree() {
+        //This only checks that both structures find the same neighbors, a timing comparison was left out
+        //because the crate has no benchmark harness and timings in unit tests are too noisy to assert on
+        let bounds = Rect::new(Point(0., 0.), Point(1000., 1000.));
+        let points = (0..60)
+            .flat_map(|x| (0..60).map(move |y| Point(x as f32 * 4.9 + 0.7, y as f32 * 4.9 + 1.3)))
+            .collect::<Vec<_>>();
+        let searches = points.iter().step_by(7).copied().collect::<Vec<_>>();
+
+        //Build and search both structures with the same uniformly distributed points
+        let mut quad = QuadTree::new(bounds);
+        for pos in points.iter() {
+            quad.insert(*pos, ()).unwrap();
+        }
+        let quad_found = searches.iter().map(|pos| quad.neighbors(*pos, 10.).len()).sum::<usize>();
+
+        let mut hash = SpatialHash::new(bounds, 10.);
+        for pos in points.iter() {
+            hash.insert(*pos, ()).unwrap();
+        }
+        let hash_found = searches.iter().map(|pos| hash.neighbors(*pos, 10.).len()).sum::<usize>();
+
+        assert_eq!(quad_found, hash_found);
+        for pos in searches.iter().take(20) {
+            assert_eq!(sorted_neighbors(&quad, *pos, 10.), sorted_neighbors(&hash, *pos, 10.));
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Systems detecting collisions between entities in the same star system
+use std::collections::{BTreeSet, HashMap};
+
+use legion::{world::SubWorld, Entity, EntityStore};
+
+use crate::{
+    component::physics::Radius,
+    event::{Event, EventBus},
+    resources::CollisionConfig,
+    state::{Point, State},
+};
+
+/// The offsets of the neighboring cells that each cell is tested against, only including half of the
+/// neighbors so that every pair of neighboring cells is only tested once
+const HALF_NEIGHBORS: [(i64, i64); 4] = [(1, 0), (1, 1), (0, 1), (-1, 1)];
+
+/// Raise a [Collision](Event::Collision) event for every pair of entities in a star system
+/// whose [Radius] components overlap, only reporting each pair once per tick
+///
+/// Bodies are sorted into a grid of cells at least as wide as the largest body, so each body is only
+/// tested against bodies in its own cell and neighboring cells. Each pair is reported with the lesser
+/// entity first, and events are raised in order of the entities so that they are the same every time the
+/// tick is run
+#[crate::on_event(tick)]
+#[legion::system]
+#[read_component(Radius)]
+pub fn collision(
+    world: &SubWorld,
+    #[resource] state: &State,
+    #[resource] config: &CollisionConfig,
+    #[resource] events: &EventBus,
+) {
+    let radius_of = |entity: Entity| -> Option<f32> {
+        Some(world.entry_ref(entity).ok()?.get_component::<Radius>().ok()?.r)
+    };
+
+    for (_, system) in state.galaxy().systems() {
+        let bodies = system
+            .entities()
+            .filter_map(|(pos, entity)| Some((pos, entity, radius_of(entity)?)))
+            .collect::<Vec<_>>();
+        //Overlapping bodies are at most two of the largest radius apart, so they are always in the same
+        //or neighboring cells
+        let max_radius = bodies.iter().fold(0f32, |max, (_, _, r)| max.max(*r));
+        let cell_size = (max_radius * 2.).max(config.min_cell_size);
+        if cell_size <= 0. {
+            continue;
+        }
+        let cell_of = |pos: Point| ((pos.x() / cell_size).floor() as i64, (pos.y() / cell_size).floor() as i64);
+        let mut cells = HashMap::<(i64, i64), Vec<usize>>::new();
+        for (idx, (pos, _, _)) in bodies.iter().enumerate() {
+            cells.entry(cell_of(*pos)).or_default().push(idx);
+        }
+
+        let mut reported = BTreeSet::new();
+        for (&(x, y), members) in cells.iter() {
+            for (n, &first) in members.iter().enumerate() {
+                let neighbors = HALF_NEIGHBORS
+                    .iter()
+                    .filter_map(|(dx, dy)| cells.get(&(x + dx, y + dy)))
+                    .flatten();
+                for &second in members[n + 1..].iter().chain(neighbors) {
+                    let (pos, entity, radius) = bodies[first];
+                    let (other_pos, other, other_radius) = bodies[second];
+                    if entity == other || pos.distance_sq(other_pos) >= (radius + other_radius).powi(2) {
+                        continue;
+                    }
+                    //Order each pair so the same pair is never reported twice
+                    reported.insert((entity.min(other), entity.max(other)));
+                }
+            }
+        }
+
+        for (a, b) in reported {
+            events.raise(Event::Collision { a, b });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{Rect, StarSystem};
+    use legion::{Resources, Schedule, World};
+
+    /// Run the collision system with the given configuration on every entity in a single star system,
+    /// returning all raised events
+    fn collide(world: &mut World, config: CollisionConfig, bodies: &[(Entity, Point)]) -> Vec<Event> {
+        let mut system = StarSystem::new(Rect::new(Point(0., 0.), Point(100., 100.)));
+        for (entity, pos) in bodies.iter() {
+            system.insert_entity(*entity, *pos).unwrap();
+        }
+        let mut state = State::default();
+        state.galaxy_mut().add_system("Sol", Point(0., 0.), system).unwrap();
+
+        let (sender, reciever) = std::sync::mpsc::channel();
+        let mut resources = Resources::default();
+        resources.insert(state);
+        resources.insert(config);
+        resources.insert(EventBus::new(sender));
+        let mut schedule = Schedule::builder().add_system(collision_system()).build();
+        schedule.execute(world, &mut resources);
+        drop(resources);
+        reciever.iter().collect()
+    }
+
+    #[test]
+    pub fn test_collision() {
+        let mut world = World::default();
+        let first = world.push((Radius { r: 5. },));
+        let second = world.push((Radius { r: 2. },));
+        let distant = world.push((Radius { r: 2. },));
+        let no_radius = world.push(());
+
+        let collisions = collide(
+            &mut world,
+            CollisionConfig::default(),
+            &[
+                (first, Point(10., 10.)),
+                (second, Point(16., 10.)),
+                (distant, Point(60., 60.)),
+                (no_radius, Point(11., 11.)),
+            ],
+        );
+        assert_eq!(collisions.len(), 1);
+        match collisions[0] {
+            Event::Collision { a, b } => assert!((a, b) == (first, second) || (a, b) == (second, first)),
+            ref other => panic!("Expected a collision event, got {:?}", other),
+        }
+    }
+
+    #[test]
+    pub fn test_unique_pairs() {
+        let mut world = World::default();
+        let bodies = [Point(20., 20.), Point(23., 21.), Point(21., 24.)]
+            .iter()
+            .map(|pos| (world.push((Radius { r: 3. },)), *pos))
+            .collect::<Vec<_>>();
+        //With cells 6 wide the first two bodies share a cell and the third is in the cell above them, so two
+        //of the overlapping pairs span neighboring cells
+        let config = CollisionConfig { min_cell_size: 6. };
+        let collisions = collide(&mut world, config, &bodies);
+        let mut pairs = collisions
+            .iter()
+            .map(|event| match event {
+                Event::Collision { a, b } => {
+                    assert!(a < b);
+                    (*a, *b)
+                }
+                other => panic!("Expected a collision event, got {:?}", other),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(pairs.len(), 3);
+        pairs.dedup();
+        assert_eq!(pairs.len(), 3);
+        for (i, (first, _)) in bodies.iter().enumerate() {
+            for (second, _) in bodies[i + 1..].iter() {
+                assert!(pairs.contains(&(*first, *second)) || pairs.contains(&(*second, *first)));
+            }
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! Systems keeping star systems' spatial indices in sync with entity [Location]s
+use legion::{systems::CommandBuffer, world::SubWorld, Entity};
+
+use crate::{
+    component::misc::{InSystem, LastLocation, Location},
+    event::{Event, EventBus},
+    state::State,
+    system::for_each_mut,
+};
+
+/// Move every entity whose [Location] or [InSystem] changed since the last tick to its new position
+/// in the spatial index of the star system named by its [InSystem] component, removing it from the
+/// star system it was in before
+///
+/// Entities naming a star system that doesn't exist are left where they were until the star system is
+/// added. Entities located outside of their star system's bounds never panic or leave the spatial
+/// index: their [Location] is clamped to the nearest point on the edge of the star system and an
+/// [OutOfBounds](Event::OutOfBounds) event is raised so that other systems can react
+#[crate::on_event(tick)]
+#[legion::system]
+#[write_component(Location)]
+#[read_component(InSystem)]
+#[write_component(LastLocation)]
+pub fn sync_location(
+    world: &mut SubWorld,
+    cmd: &mut CommandBuffer,
+    #[resource] state: &mut State,
+    #[resource] events: &EventBus,
+) {
+    for_each_mut::<(Entity, &mut Location, &InSystem, Option<&mut LastLocation>)>(world, |(entity, location, in_system, last)| {
+        if let Some(ref last) = last {
+            if last.loc == location.loc && last.system == in_system.name {
+                return;
+            }
+        }
+        let galaxy = state.galaxy_mut();
+        let system = match galaxy.system_mut(&in_system.name) {
+            Some(system) => system,
+            None => return,
+        };
+        let bounds = system.bounds();
+        if !bounds.contains(location.loc) {
+            location.loc = bounds.clamp(location.loc);
+            events.raise(Event::OutOfBounds { entity: *entity });
+        }
+        //Without a last location the entity may have been placed in the system already, so find where
+        let from = match last {
+            Some(ref last) if last.system == in_system.name => Some(last.loc),
+            Some(_) => None,
+            None => system.entities().find(|(_, other)| other == entity).map(|(pos, _)| pos),
+        };
+        if system.relocate(*entity, from.unwrap_or(location.loc), location.loc).is_err() {
+            return;
+        }
+
+        match last {
+            Some(last) => {
+                //Remove the entity from the star system it left
+                if last.system != in_system.name {
+                    if let Some(old) = galaxy.system_mut(&last.system) {
+                        old.remove_entity(*entity, last.loc);
+                    }
+                }
+                last.loc = location.loc;
+                last.system.clone_from(&in_system.name);
+            }
+            None => cmd.add_component(
+                *entity,
+                LastLocation {
+                    loc: location.loc,
+                    system: in_system.name.clone(),
+                },
+            ),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{Point, Rect, StarSystem};
+    use legion::{Resources, Schedule, World};
+
+    /// Create a state with two empty star systems named `Sol` and `Vega`
+    fn two_systems() -> State {
+        let mut state = State::default();
+        for (name, pos) in [("Sol", Point(0., 0.)), ("Vega", Point(100., 100.))] {
+            let system = StarSystem::new(Rect::new(Point(0., 0.), Point(100., 100.)));
+            state.galaxy_mut().add_system(name, pos, system).unwrap();
+        }
+        state
+    }
+
+    #[test]
+    pub fn test_sync_location() {
+        let mut world = World::default();
+        let ship = world.push((Location { loc: Point(10., 10.) }, InSystem { name: "Sol".to_owned() }));
+        let mut state = two_systems();
+        state.galaxy_mut().system_mut("Sol").unwrap().insert_entity(ship, Point(10., 10.)).unwrap();
+
+        let mut resources = Resources::default();
+        resources.insert(state);
+        resources.insert(EventBus::new(std::sync::mpsc::channel().0));
+        let mut schedule = Schedule::builder().add_system(sync_location_system()).build();
+        schedule.execute(&mut world, &mut resources);
+
+        world.entry(ship).unwrap().get_component_mut::<Location>().unwrap().loc = Point(70., 40.);
+        schedule.execute(&mut world, &mut resources);
+        world.entry(ship).unwrap().get_component_mut::<Location>().unwrap().loc = Point(80., 90.);
+        schedule.execute(&mut world, &mut resources);
+
+        let state = resources.get::<State>().unwrap();
+        let system = state.galaxy().system("Sol").unwrap();
+        assert_eq!(system.entities_near(Point(80., 90.), 1.), vec![(Point(80., 90.), ship)]);
+        assert!(system.entities_near(Point(10., 10.), 1.).is_empty());
+        assert!(system.entities_near(Point(70., 40.), 1.).is_empty());
+        assert_eq!(system.entities().count(), 1);
+    }
+
+    #[test]
+    pub fn test_change_system() {
+        let mut world = World::default();
+        let ship = world.push((Location { loc: Point(10., 10.) }, InSystem { name: "Sol".to_owned() }));
+        let mut resources = Resources::default();
+        resources.insert(two_systems());
+        resources.insert(EventBus::new(std::sync::mpsc::channel().0));
+        let mut schedule = Schedule::builder().add_system(sync_location_system()).build();
+        schedule.execute(&mut world, &mut resources);
+        assert_eq!(
+            resources.get::<State>().unwrap().galaxy().system("Sol").unwrap().entities().collect::<Vec<_>>(),
+            vec![(Point(10., 10.), ship)]
+        );
+
+        world.entry(ship).unwrap().get_component_mut::<InSystem>().unwrap().name = "Vega".to_owned();
+        schedule.execute(&mut world, &mut resources);
+
+        let state = resources.get::<State>().unwrap();
+        assert_eq!(state.galaxy().system("Sol").unwrap().entities().count(), 0);
+        assert_eq!(
+            state.galaxy().system("Vega").unwrap().entities_near(Point(10., 10.), 1.),
+            vec![(Point(10., 10.), ship)]
+        );
+    }
+
+    #[test]
+    pub fn test_out_of_bounds() {
+        let mut world = World::default();
+        let ship = world.push((Location { loc: Point(50., 50.) }, InSystem { name: "Sol".to_owned() }));
+        let (sender, reciever) = std::sync::mpsc::channel();
+        let mut resources = Resources::default();
+        resources.insert(two_systems());
+        resources.insert(EventBus::new(sender));
+        let mut schedule = Schedule::builder().add_system(sync_location_system()).build();
+        schedule.execute(&mut world, &mut resources);
+        assert!(reciever.try_recv().is_err());
+
+        //Fly past the edge of the star system
+        world.entry(ship).unwrap().get_component_mut::<Location>().unwrap().loc = Point(130., -20.);
+        schedule.execute(&mut world, &mut resources);
+
+        assert_eq!(world.entry(ship).unwrap().get_component::<Location>().unwrap().loc, Point(100., 0.));
+        match reciever.try_recv() {
+            Ok(Event::OutOfBounds { entity }) => assert_eq!(entity, ship),
+            other => panic!("Expected an out of bounds event, got {:?}", other),
+        }
+        let state = resources.get::<State>().unwrap();
+        let system = state.galaxy().system("Sol").unwrap();
+        assert_eq!(system.entities().collect::<Vec<_>>(), vec![(Point(100., 0.), ship)]);
+    }
+}
@@ -1 +1,130 @@
-//! System function definitions
+//! System function definitions, and helpers for writing systems more concisely than with legion's
+//! query types directly
+use legion::{query::View, EntityStore, IntoQuery};
+use parking_lot::Mutex;
+use std::fmt;
+
+pub mod collision;
+pub mod location;
+pub mod power;
+
+/// An error returned by a system marked with [fallible](crate::fallible)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SystemError {
+    /// A description of what went wrong
+    msg: String,
+}
+
+impl SystemError {
+    /// Create a new error with the given description
+    pub fn new(msg: impl Into<String>) -> Self {
+        Self { msg: msg.into() }
+    }
+}
+
+impl fmt::Display for SystemError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl std::error::Error for SystemError {}
+
+/// The `Errors` resource collects every error returned by [fallible](crate::fallible) systems, along with
+/// the name of the system that returned it, until the errors are drained
+#[derive(Debug, Default)]
+pub struct Errors(Mutex<Vec<(&'static str, SystemError)>>);
+
+impl Errors {
+    /// Record an error returned by the system with the given name
+    pub fn push(&self, system: &'static str, error: SystemError) {
+        self.0.lock().push((system, error));
+    }
+
+    /// Remove and return every recorded error in the order they were recorded
+    pub fn drain(&self) -> Vec<(&'static str, SystemError)> {
+        std::mem::take(&mut *self.0.lock())
+    }
+
+    /// Check if no errors have been recorded
+    pub fn is_empty(&self) -> bool {
+        self.0.lock().is_empty()
+    }
+}
+
+/// Run `f` on the components of every entity that matches the view `V`, where `V` is a component
+/// reference or tuple of references like `(&Hull, &mut Powered)`
+///
+/// This is shorthand for `V::query().for_each_mut(world, f)`, and builds a new query every time it is
+/// called
+/// ## Example
+/// ```ignore
+/// for_each_mut::<(&Powered, &mut Hull)>(world, |(powered, hull)| {
+///     ...
+/// });
+/// ```
+pub fn for_each_mut<'world, V: IntoQuery>(world: &'world mut impl EntityStore, f: impl FnMut(<V::View as View<'world>>::Element)) {
+    V::query().for_each_mut(world, f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::misc::{Location, Name};
+    use crate::state::Point;
+    use legion::{Entity, Resources, Schedule, World};
+
+    #[crate::fallible]
+    #[legion::system]
+    fn check_name(#[resource] name: &Name) -> Result<(), SystemError> {
+        let first = name.name.chars().next().ok_or_else(|| SystemError::new("Name is empty"))?;
+        if !first.is_uppercase() {
+            return Err(SystemError::new(format!("{} is not capitalized", name.name)));
+        }
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_fallible() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Errors::default());
+        let mut schedule = Schedule::builder().add_system(check_name_system()).build();
+        for name in ["Enterprise", "voyager", ""] {
+            resources.insert(Name { name: name.to_owned() });
+            schedule.execute(&mut world, &mut resources);
+        }
+
+        let errors = resources.get::<Errors>().unwrap().drain();
+        assert_eq!(
+            errors,
+            vec![
+                ("check_name", SystemError::new("voyager is not capitalized")),
+                ("check_name", SystemError::new("Name is empty")),
+            ]
+        );
+        assert!(resources.get::<Errors>().unwrap().is_empty());
+    }
+
+    #[test]
+    pub fn test_for_each_mut() {
+        let mut world = World::default();
+        let named = world.push((Name { name: "Buoy".to_owned() },));
+        let both = world.push((Name { name: "Ship".to_owned() }, Location { loc: Point(0., 0.) }));
+        let located = world.push((Location { loc: Point(1., 1.) },));
+
+        let mut visited = Vec::new();
+        for_each_mut::<(Entity, &Name, &mut Location)>(&mut world, |(entity, name, location)| {
+            visited.push((*entity, name.name.clone()));
+            location.loc = Point(5., 5.);
+        });
+        assert_eq!(visited, vec![(both, "Ship".to_owned())]);
+        assert_eq!(world.entry(both).unwrap().get_component::<Location>().unwrap().loc, Point(5., 5.));
+        assert_eq!(world.entry(located).unwrap().get_component::<Location>().unwrap().loc, Point(1., 1.));
+
+        let mut visited = Vec::new();
+        for_each_mut::<(Entity, &Name)>(&mut world, |(entity, _)| visited.push(*entity));
+        visited.sort_by_key(|entity| *entity == both);
+        assert_eq!(visited, vec![named, both]);
+    }
+}
@@ -0,0 +1,120 @@
+//! Systems enforcing the power budget of powered entities
+use legion::{systems::CommandBuffer, world::SubWorld, Entity, EntityStore, IntoQuery};
+use uom::si::{f32::Power, power::watt};
+
+use crate::component::{
+    hull::Hull,
+    power::{Online, PowerDraw, Powered},
+};
+
+/// Sum the power draw of every online module fitted to a hull and take the lowest priority
+/// modules offline until the total draw fits in the power availible to the hull
+#[crate::on_event(tick)]
+#[legion::system]
+#[read_component(Hull)]
+#[read_component(Powered)]
+#[read_component(PowerDraw)]
+#[read_component(Online)]
+pub fn power_budget(world: &mut SubWorld, cmd: &mut CommandBuffer) {
+    let mut query = <(&Hull, &Powered)>::query();
+    for (hull, powered) in query.iter(world) {
+        //Collect the draw of all fitted modules that are online
+        let mut modules = hull
+            .fitted()
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, module)| {
+                let entry = world.entry_ref(*module).ok()?;
+                entry.get_component::<Online>().ok()?;
+                let draw = entry.get_component::<PowerDraw>().ok()?;
+                Some((slot, *module, *draw))
+            })
+            .collect::<Vec<(usize, Entity, PowerDraw)>>();
+
+        let mut total = modules
+            .iter()
+            .fold(Power::new::<watt>(0.), |total, (_, _, draw)| total + draw.watts);
+        if total <= powered.pwr {
+            continue;
+        }
+
+        //Take modules offline by lowest priority first, breaking ties with the most recently fitted module
+        modules.sort_by(|(slot, _, draw), (other_slot, _, other_draw)| {
+            draw.priority
+                .cmp(&other_draw.priority)
+                .then(other_slot.cmp(slot))
+        });
+        for (_, module, draw) in modules {
+            if total <= powered.pwr {
+                break;
+            }
+            cmd.remove_component::<Online>(module);
+            total -= draw.watts;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::hull::HullSize;
+    use legion::{Resources, Schedule, World};
+
+    /// Spawn an online module drawing the given power at the given priority
+    fn module(world: &mut World, watts: f32, priority: u8) -> Entity {
+        world.push((
+            PowerDraw {
+                watts: Power::new::<watt>(watts),
+                priority,
+            },
+            Online,
+        ))
+    }
+
+    /// Fit the modules to a hull in order, run the power budget system with the given power availible,
+    /// and return which of the modules are still online
+    fn budget(world: &mut World, modules: &[Entity], watts: f32) -> Vec<bool> {
+        let mut hull = Hull::new(HullSize::Medium);
+        for module in modules.iter() {
+            hull.fit(*module).unwrap();
+        }
+        world.push((
+            hull,
+            Powered {
+                pwr: Power::new::<watt>(watts),
+            },
+        ));
+
+        let mut schedule = Schedule::builder().add_system(power_budget_system()).build();
+        schedule.execute(world, &mut Resources::default());
+        modules
+            .iter()
+            .map(|module| world.entry_ref(*module).unwrap().get_component::<Online>().is_ok())
+            .collect()
+    }
+
+    #[test]
+    pub fn test_power_budget() {
+        let mut world = World::default();
+        let shields = module(&mut world, 50., 3);
+        let engines = module(&mut world, 40., 2);
+        let sensors = module(&mut world, 20., 1);
+        let lights = module(&mut world, 10., 1);
+
+        let online = budget(&mut world, &[shields, engines, sensors, lights], 95.);
+        assert_eq!(online, vec![true, true, false, false]);
+    }
+
+    #[test]
+    pub fn test_power_budget_tie() {
+        let mut world = World::default();
+        let shields = module(&mut world, 50., 3);
+        let sensors = module(&mut world, 30., 1);
+        let scanner = module(&mut world, 30., 1);
+
+        //Shedding either of the equal priority modules balances the budget, so only the later fitted one
+        //goes offline
+        let online = budget(&mut world, &[shields, sensors, scanner], 85.);
+        assert_eq!(online, vec![true, true, false]);
+    }
+}